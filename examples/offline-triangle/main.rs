@@ -1,9 +1,9 @@
 use std::{f32::consts::TAU, time::Instant};
 
 use anyhow::{Ok, Result};
-use image::{ImageBuffer, Rgb};
+use image::RgbaImage;
 use tufa::{
-    bindings::buffer::mutability::Immutable,
+    bindings::texture::format::{Depth, Rgba8},
     export::{
         nalgebra::{Matrix4, Vector2, Vector3, Vector4},
         wgpu::{include_wgsl, ShaderStages},
@@ -26,14 +26,14 @@ fn main() -> Result<()> {
         Vertex::new(Vector4::new(-0.5, -0.5, 0.0, 1.0), Vector2::new(0.0, 0.0)),
         Vertex::new(Vector4::new(0.5, -0.5, 0.0, 1.0), Vector2::new(1.0, 0.0)),
     ]);
-    let index = gpu.create_index(&[0, 1, 2]);
+    let index = gpu.create_index(&[0u16, 1, 2]);
     let mut render = gpu
         .render_pipeline(include_wgsl!("shader.wgsl"))
         .bind(&uniform, ShaderStages::VERTEX)
         .finish();
 
-    let (texture, depth) = (gpu.create_texture_2d(SIZE), gpu.create_texture_2d(SIZE));
-    let staging = gpu.create_storage_empty::<_, Immutable>(4 * (SIZE.x * SIZE.y) as u64);
+    let texture = gpu.create_texture_2d::<Rgba8>(SIZE);
+    let depth = gpu.create_texture_2d::<Depth>(SIZE);
 
     let start = Instant::now();
     let aspect = SIZE.x as f32 / SIZE.y as f32;
@@ -46,14 +46,11 @@ fn main() -> Result<()> {
             render.draw(render_pass, &index, &vertex, 0..3)
         });
 
-        texture.copy_to_buffer(&staging);
-        staging.download_async(move |result: Vec<u32>| {
-            ImageBuffer::from_par_fn(SIZE.x, SIZE.y, |x, y| {
-                let color = result[(y * SIZE.x + x) as usize];
-                Rgb([color as u8, (color >> 8) as u8, (color >> 16) as u8])
-            })
-            .save(format!("out/{i:0>3}-out.png"))
-            .unwrap();
+        texture.download_async(move |data| {
+            RgbaImage::from_raw(SIZE.x, SIZE.y, data)
+                .unwrap()
+                .save(format!("out/{i:0>3}-out.png"))
+                .unwrap();
         });
     }
 
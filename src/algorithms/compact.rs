@@ -0,0 +1,62 @@
+use crate::bindings::buffer::{
+    array_stride,
+    mutability::{Mutability, Mutable},
+    RawStorage, StorageBuffer,
+};
+
+use super::{kernel, len, scan::scan_into, workgroups, ReduceOp, Scalar, WORKGROUP_SIZE};
+
+/// The output of [`compact`].
+pub struct Compaction<T> {
+    /// The kept elements, in their original order. The buffer is the same size
+    /// as the input, so only the first `count` elements are meaningful.
+    pub values: StorageBuffer<Vec<T>, Mutable>,
+    /// The number of elements that were kept.
+    pub count: StorageBuffer<u32, Mutable>,
+}
+
+/// Keeps only the elements of `input` whose entry in `flags` is non-zero,
+/// packing them to the front of the output while preserving their order.
+///
+/// `flags` must have at least as many elements as `input`, each being either
+/// zero or one.
+///
+/// ```rust,ignore
+/// let alive = algorithms::compact(&particles, &flags);
+/// let count = alive.count.download();
+/// ```
+pub fn compact<T: Scalar, Mut: Mutability, FlagMut: Mutability>(
+    input: &StorageBuffer<Vec<T>, Mut>,
+    flags: &StorageBuffer<Vec<u32>, FlagMut>,
+) -> Compaction<T> {
+    assert!(
+        len(flags) >= len(input),
+        "Compaction needs a flag for every element"
+    );
+
+    let gpu = &input.gpu;
+    let len = len(input);
+
+    let values =
+        gpu.create_storage_empty::<Vec<T>, Mutable>(len.max(1) as u64 * array_stride::<T>());
+    let count = gpu.create_storage(&0);
+
+    if len > 0 {
+        let size = len as u64 * array_stride::<u32>();
+        let offsets = gpu.scratch.get(gpu, "compact.offsets", 0, size);
+        scan_into::<u32>(gpu, ReduceOp::Sum, flags.buffer, offsets, len);
+
+        let uniform = gpu.create_uniform(&len);
+        kernel::<T>(gpu, include_str!("compact.wgsl"), ReduceOp::Sum)
+            .bind(&uniform)
+            .bind(&RawStorage(input.buffer))
+            .bind(&RawStorage(flags.buffer))
+            .bind(&RawStorage(offsets))
+            .bind(&values)
+            .bind(&count)
+            .finish()
+            .queue_dispatch(workgroups(len.div_ceil(WORKGROUP_SIZE)));
+    }
+
+    Compaction { values, count }
+}
@@ -0,0 +1,31 @@
+// Moves every flagged element to its scanned offset, and writes the number of
+// kept elements to `count`.
+
+@group(0) @binding(0) var<uniform> len: u32;
+@group(0) @binding(1) var<storage, read_write> input: array<$TYPE>;
+@group(0) @binding(2) var<storage, read_write> flags: array<u32>;
+@group(0) @binding(3) var<storage, read_write> offsets: array<u32>;
+@group(0) @binding(4) var<storage, read_write> output: array<$TYPE>;
+@group(0) @binding(5) var<storage, read_write> count: u32;
+
+@compute
+@workgroup_size(256, 1, 1)
+fn main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) local: u32
+) {
+    let index = (group.x + group.y * groups.x) * 256u + local;
+    if index >= len {
+        return;
+    }
+
+    let keep = flags[index] != 0u;
+    if keep {
+        output[offsets[index]] = input[index];
+    }
+
+    if index == len - 1u {
+        count = offsets[index] + select(0u, 1u, keep);
+    }
+}
@@ -0,0 +1,141 @@
+//! Parallel primitives that run entirely on the GPU, like reductions, scans and sorting.
+//!
+//! Each operation takes a [`StorageBuffer<Vec<T>>`](crate::bindings::buffer::StorageBuffer) and adds its passes to the
+//! dispatch queue, so they will run in order with any other queued work on
+//! the next dispatch, render pass, or call to [`Gpu::flush_dispatch_queue`](crate::gpu::Gpu::flush_dispatch_queue).
+//! Results are either written in place or returned as new storage buffers that
+//! can be bound to your own pipelines or downloaded.
+//!
+//! All operations work on the elements the buffer was created with or last
+//! uploaded, given by [`StorageBuffer::len`](crate::bindings::buffer::StorageBuffer::len).
+//! Any temporary buffers and pipelines they need are cached on the [`Gpu`] and
+//! reused between calls.
+
+use encase::{
+    internal::{CreateFrom, ReadFrom, WriteInto},
+    ShaderSize, ShaderType,
+};
+use nalgebra::Vector3;
+
+use crate::{
+    bindings::buffer::{mutability::Mutability, StorageBuffer},
+    gpu::Gpu,
+    pipeline::compute::ComputePipelineBuilder,
+};
+
+mod compact;
+mod reduce;
+mod scan;
+mod sort;
+
+pub use compact::{compact, Compaction};
+pub use reduce::reduce;
+pub use scan::exclusive_scan;
+pub use sort::{sort_keys, sort_pairs};
+
+/// Number of invocations in each workgroup of the built-in kernels.
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Scalar types supported by the parallel primitives.
+pub trait Scalar:
+    ShaderType + ShaderSize + WriteInto + ReadFrom + CreateFrom + Copy + Send + Sync + 'static
+{
+    /// The name of the type in WGSL.
+    const WGSL: &'static str;
+    const ZERO: Self;
+    const MIN: Self;
+    const MAX: Self;
+
+    /// Formats the value as a WGSL literal.
+    fn literal(self) -> String;
+}
+
+impl Scalar for u32 {
+    const WGSL: &'static str = "u32";
+    const ZERO: Self = 0;
+    const MIN: Self = u32::MIN;
+    const MAX: Self = u32::MAX;
+
+    fn literal(self) -> String {
+        format!("{self}u")
+    }
+}
+
+impl Scalar for i32 {
+    const WGSL: &'static str = "i32";
+    const ZERO: Self = 0;
+    const MIN: Self = i32::MIN;
+    const MAX: Self = i32::MAX;
+
+    fn literal(self) -> String {
+        // The abstract int lets us represent `i32::MIN`, which `-2147483648i` can't.
+        format!("i32({self})")
+    }
+}
+
+impl Scalar for f32 {
+    const WGSL: &'static str = "f32";
+    const ZERO: Self = 0.0;
+    const MIN: Self = f32::MIN;
+    const MAX: Self = f32::MAX;
+
+    fn literal(self) -> String {
+        format!("{self:?}f")
+    }
+}
+
+/// The associative operation used to combine elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReduceOp {
+    Sum,
+    Min,
+    Max,
+}
+
+impl ReduceOp {
+    /// The value that leaves any other value unchanged under this operation.
+    pub fn identity<T: Scalar>(self) -> T {
+        match self {
+            ReduceOp::Sum => T::ZERO,
+            ReduceOp::Min => T::MAX,
+            ReduceOp::Max => T::MIN,
+        }
+    }
+
+    fn wgsl(self) -> &'static str {
+        match self {
+            ReduceOp::Sum => "a + b",
+            ReduceOp::Min => "min(a, b)",
+            ReduceOp::Max => "max(a, b)",
+        }
+    }
+}
+
+/// Splits `groups` workgroups over the x and y dimensions, as a single
+/// dimension is limited to 65535 workgroups. Kernels recover the linear
+/// workgroup index with `id.x + id.y * count.x`.
+pub(crate) fn workgroups(groups: u32) -> Vector3<u32> {
+    let groups = groups.max(1);
+    let x = groups.min(u16::MAX as u32);
+    Vector3::new(x, groups.div_ceil(x), 1)
+}
+
+/// Number of elements the buffer holds, ignoring any left in its allocation
+/// from larger uploads.
+fn len<T: Scalar, Mut: Mutability>(buffer: &StorageBuffer<Vec<T>, Mut>) -> u32 {
+    buffer.len() as u32
+}
+
+/// Creates a pipeline for a built-in kernel that is generic over the element
+/// type and operation.
+fn kernel<T: Scalar>(gpu: &Gpu, source: &str, op: ReduceOp) -> ComputePipelineBuilder {
+    let identity = op.identity::<T>().literal();
+    gpu.kernel(
+        source,
+        &[
+            ("$TYPE", T::WGSL),
+            ("$OP", op.wgsl()),
+            ("$IDENTITY", &identity),
+        ],
+    )
+}
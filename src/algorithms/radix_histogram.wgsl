@@ -0,0 +1,41 @@
+// Counts how many keys in each block of 256 have each 4-bit digit. Counts are
+// stored digit-major so an exclusive scan gives every (digit, block) pair its
+// offset in the sorted output.
+
+struct Params {
+    len: u32,
+    shift: u32,
+    blocks: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> keys: array<u32>;
+@group(0) @binding(2) var<storage, read_write> histogram: array<u32>;
+
+var<workgroup> counts: array<atomic<u32>, 16>;
+
+@compute
+@workgroup_size(256, 1, 1)
+fn main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) local: u32
+) {
+    let block = group.x + group.y * groups.x;
+    let index = block * 256u + local;
+
+    if local < 16u {
+        atomicStore(&counts[local], 0u);
+    }
+    workgroupBarrier();
+
+    if index < params.len {
+        let digit = (keys[index] >> params.shift) & 15u;
+        atomicAdd(&counts[digit], 1u);
+    }
+    workgroupBarrier();
+
+    if local < 16u && block < params.blocks {
+        histogram[local * params.blocks + block] = atomicLoad(&counts[local]);
+    }
+}
@@ -0,0 +1,53 @@
+// Moves each key (and its value) to its place in the output for this digit.
+// Keys with the same digit keep their relative order, making the sort stable.
+
+struct Params {
+    len: u32,
+    shift: u32,
+    blocks: u32,
+}
+
+const PAIRS: bool = $PAIRS;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> keys_in: array<u32>;
+@group(0) @binding(2) var<storage, read_write> keys_out: array<u32>;
+@group(0) @binding(3) var<storage, read_write> offsets: array<u32>;
+@group(0) @binding(4) var<storage, read_write> values_in: array<u32>;
+@group(0) @binding(5) var<storage, read_write> values_out: array<u32>;
+
+var<workgroup> digits: array<u32, 256>;
+
+@compute
+@workgroup_size(256, 1, 1)
+fn main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) local: u32
+) {
+    let block = group.x + group.y * groups.x;
+    let index = block * 256u + local;
+
+    // Out of range invocations get a digit that never matches a real one.
+    var digit = 16u;
+    if index < params.len {
+        digit = (keys_in[index] >> params.shift) & 15u;
+    }
+    digits[local] = digit;
+    workgroupBarrier();
+
+    if index >= params.len {
+        return;
+    }
+
+    var rank = 0u;
+    for (var i = 0u; i < local; i++) {
+        rank += select(0u, 1u, digits[i] == digit);
+    }
+
+    let destination = offsets[digit * params.blocks + block] + rank;
+    keys_out[destination] = keys_in[index];
+    if PAIRS {
+        values_out[destination] = values_in[index];
+    }
+}
@@ -0,0 +1,56 @@
+use crate::bindings::buffer::{
+    array_stride,
+    mutability::{Mutability, Mutable},
+    RawStorage, StorageBuffer,
+};
+
+use super::{kernel, len, workgroups, ReduceOp, Scalar};
+
+/// Number of elements each workgroup combines in a single pass.
+const BLOCK_SIZE: u32 = 512;
+
+/// Combines every element of `input` with `op`, returning a buffer holding the
+/// single result.
+///
+/// ```rust,ignore
+/// let total = algorithms::reduce(&buffer, ReduceOp::Sum);
+/// println!("Sum: {}", total.download());
+/// ```
+pub fn reduce<T: Scalar, Mut: Mutability>(
+    input: &StorageBuffer<Vec<T>, Mut>,
+    op: ReduceOp,
+) -> StorageBuffer<T, Mutable> {
+    let gpu = &input.gpu;
+    let output = gpu.create_storage(&op.identity::<T>());
+
+    let mut len = len(input);
+    let mut source = input.buffer;
+    let mut level = 0;
+
+    while len > 0 {
+        let blocks = len.div_ceil(BLOCK_SIZE);
+        let destination = if blocks == 1 {
+            output.buffer
+        } else {
+            let size = blocks as u64 * array_stride::<T>();
+            gpu.scratch.get(gpu, "reduce.partials", level % 2, size)
+        };
+
+        let uniform = gpu.create_uniform(&len);
+        kernel::<T>(gpu, include_str!("reduce.wgsl"), op)
+            .bind(&uniform)
+            .bind(&RawStorage(source))
+            .bind(&RawStorage(destination))
+            .finish()
+            .queue_dispatch(workgroups(blocks));
+
+        if blocks == 1 {
+            break;
+        }
+
+        (len, source) = (blocks, destination);
+        level += 1;
+    }
+
+    output
+}
@@ -0,0 +1,44 @@
+// Combines each block of 512 elements into a single value.
+
+@group(0) @binding(0) var<uniform> len: u32;
+@group(0) @binding(1) var<storage, read_write> input: array<$TYPE>;
+@group(0) @binding(2) var<storage, read_write> output: array<$TYPE>;
+
+var<workgroup> scratch: array<$TYPE, 256>;
+
+fn op(a: $TYPE, b: $TYPE) -> $TYPE {
+    return $OP;
+}
+
+fn load(index: u32) -> $TYPE {
+    if index < len {
+        return input[index];
+    }
+
+    return $IDENTITY;
+}
+
+@compute
+@workgroup_size(256, 1, 1)
+fn main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) local: u32
+) {
+    let block = group.x + group.y * groups.x;
+    let base = block * 512u;
+
+    scratch[local] = op(load(base + local), load(base + local + 256u));
+    workgroupBarrier();
+
+    for (var stride = 128u; stride > 0u; stride >>= 1u) {
+        if local < stride {
+            scratch[local] = op(scratch[local], scratch[local + stride]);
+        }
+        workgroupBarrier();
+    }
+
+    if local == 0u && base < len {
+        output[block] = scratch[0];
+    }
+}
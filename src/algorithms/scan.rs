@@ -0,0 +1,83 @@
+use crate::{
+    bindings::buffer::{
+        array_stride,
+        mutability::{Mutability, Mutable},
+        RawStorage, StorageBuffer,
+    },
+    gpu::Gpu,
+    misc::ids::BufferId,
+};
+
+use super::{kernel, len, workgroups, ReduceOp, Scalar, WORKGROUP_SIZE};
+
+/// Computes the exclusive prefix scan of `input` with `op`. Each element of
+/// the output is the combination of every element before it in the input,
+/// starting with the identity of `op`.
+///
+/// ```rust,ignore
+/// // [3, 1, 4, 1, 5] => [0, 3, 4, 8, 9]
+/// let offsets = algorithms::exclusive_scan(&counts, ReduceOp::Sum);
+/// ```
+pub fn exclusive_scan<T: Scalar, Mut: Mutability>(
+    input: &StorageBuffer<Vec<T>, Mut>,
+    op: ReduceOp,
+) -> StorageBuffer<Vec<T>, Mutable> {
+    let gpu = &input.gpu;
+    let len = len(input);
+    let output =
+        gpu.create_storage_empty::<Vec<T>, Mutable>(len.max(1) as u64 * array_stride::<T>());
+
+    scan_into::<T>(gpu, op, input.buffer, output.buffer, len);
+    output
+}
+
+/// Queues an exclusive scan of the first `len` elements of `input` into
+/// `output`. Blocks are scanned independently, then their totals are scanned
+/// recursively and added back in.
+pub(crate) fn scan_into<T: Scalar>(
+    gpu: &Gpu,
+    op: ReduceOp,
+    input: BufferId,
+    output: BufferId,
+    len: u32,
+) {
+    scan_level::<T>(gpu, op, input, output, len, 0);
+}
+
+fn scan_level<T: Scalar>(
+    gpu: &Gpu,
+    op: ReduceOp,
+    input: BufferId,
+    output: BufferId,
+    len: u32,
+    level: u32,
+) {
+    if len == 0 {
+        return;
+    }
+
+    let blocks = len.div_ceil(WORKGROUP_SIZE);
+    let size = blocks as u64 * array_stride::<T>();
+    let sums = gpu.scratch.get(gpu, "scan.sums", level, size);
+
+    let uniform = gpu.create_uniform(&len);
+    kernel::<T>(gpu, include_str!("scan.wgsl"), op)
+        .bind(&uniform)
+        .bind(&RawStorage(input))
+        .bind(&RawStorage(output))
+        .bind(&RawStorage(sums))
+        .finish()
+        .queue_dispatch(workgroups(blocks));
+
+    if blocks > 1 {
+        let offsets = gpu.scratch.get(gpu, "scan.offsets", level, size);
+        scan_level::<T>(gpu, op, sums, offsets, blocks, level + 1);
+
+        kernel::<T>(gpu, include_str!("scan_add.wgsl"), op)
+            .bind(&uniform)
+            .bind(&RawStorage(output))
+            .bind(&RawStorage(offsets))
+            .finish()
+            .queue_dispatch(workgroups(blocks));
+    }
+}
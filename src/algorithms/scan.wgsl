@@ -0,0 +1,53 @@
+// Exclusive scan of each block of 256 elements. The total of each block is
+// written to `sums` so the blocks can be joined in a later pass.
+
+@group(0) @binding(0) var<uniform> len: u32;
+@group(0) @binding(1) var<storage, read_write> input: array<$TYPE>;
+@group(0) @binding(2) var<storage, read_write> output: array<$TYPE>;
+@group(0) @binding(3) var<storage, read_write> sums: array<$TYPE>;
+
+var<workgroup> scratch: array<$TYPE, 256>;
+
+fn op(a: $TYPE, b: $TYPE) -> $TYPE {
+    return $OP;
+}
+
+@compute
+@workgroup_size(256, 1, 1)
+fn main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) local: u32
+) {
+    let block = group.x + group.y * groups.x;
+    let index = block * 256u + local;
+
+    var value = $IDENTITY;
+    if index < len {
+        value = input[index];
+    }
+    scratch[local] = value;
+    workgroupBarrier();
+
+    for (var offset = 1u; offset < 256u; offset <<= 1u) {
+        var next = scratch[local];
+        if local >= offset {
+            next = op(scratch[local - offset], next);
+        }
+        workgroupBarrier();
+        scratch[local] = next;
+        workgroupBarrier();
+    }
+
+    if index < len {
+        var exclusive = $IDENTITY;
+        if local > 0u {
+            exclusive = scratch[local - 1u];
+        }
+        output[index] = exclusive;
+    }
+
+    if local == 255u && block * 256u < len {
+        sums[block] = scratch[255];
+    }
+}
@@ -0,0 +1,24 @@
+// Combines the scanned block totals into every element of their block.
+
+@group(0) @binding(0) var<uniform> len: u32;
+@group(0) @binding(1) var<storage, read_write> data: array<$TYPE>;
+@group(0) @binding(2) var<storage, read_write> offsets: array<$TYPE>;
+
+fn op(a: $TYPE, b: $TYPE) -> $TYPE {
+    return $OP;
+}
+
+@compute
+@workgroup_size(256, 1, 1)
+fn main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) local: u32
+) {
+    let block = group.x + group.y * groups.x;
+    let index = block * 256u + local;
+
+    if index < len {
+        data[index] = op(offsets[block], data[index]);
+    }
+}
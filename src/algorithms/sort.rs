@@ -0,0 +1,113 @@
+use encase::ShaderType;
+
+use crate::{
+    bindings::buffer::{mutability::Mutability, RawStorage, StorageBuffer},
+    gpu::Gpu,
+    misc::ids::BufferId,
+};
+
+use super::{len, scan::scan_into, workgroups, ReduceOp, WORKGROUP_SIZE};
+
+/// Number of key bits sorted by each pass.
+const RADIX_BITS: u32 = 4;
+const RADIX: u32 = 1 << RADIX_BITS;
+
+#[derive(ShaderType)]
+struct Params {
+    len: u32,
+    shift: u32,
+    blocks: u32,
+}
+
+/// Sorts `keys` in ascending order, in place.
+///
+/// ```rust,ignore
+/// algorithms::sort_keys(&cells);
+/// ```
+pub fn sort_keys<Mut: Mutability>(keys: &StorageBuffer<Vec<u32>, Mut>) {
+    radix_sort(&keys.gpu, keys.buffer, None, len(keys));
+}
+
+/// Sorts `keys` in ascending order, in place, applying the same permutation to
+/// `values`. The sort is stable, so values with equal keys keep their order.
+///
+/// ```rust,ignore
+/// // Order particle indices by the cell they are in.
+/// algorithms::sort_pairs(&cells, &indices);
+/// ```
+pub fn sort_pairs<KeyMut: Mutability, ValueMut: Mutability>(
+    keys: &StorageBuffer<Vec<u32>, KeyMut>,
+    values: &StorageBuffer<Vec<u32>, ValueMut>,
+) {
+    assert!(
+        len(values) >= len(keys),
+        "Sorting pairs needs a value for every key"
+    );
+
+    radix_sort(&keys.gpu, keys.buffer, Some(values.buffer), len(keys));
+}
+
+/// Queues a least significant digit radix sort. Each pass histograms one
+/// digit of every block, scans the histograms to find where each block's keys
+/// go, then scatters them into the other buffer. There is an even number of
+/// passes, so the sorted keys end up back in the original buffers.
+fn radix_sort(gpu: &Gpu, keys: BufferId, values: Option<BufferId>, len: u32) {
+    if len <= 1 {
+        return;
+    }
+
+    let size = len as u64 * 4;
+    let blocks = len.div_ceil(WORKGROUP_SIZE);
+    let histogram_size = (RADIX * blocks) as u64 * 4;
+
+    let scratch = &gpu.scratch;
+    let temp_keys = scratch.get(gpu, "sort.keys", 0, size);
+    let histogram = scratch.get(gpu, "sort.histogram", 0, histogram_size);
+    let offsets = scratch.get(gpu, "sort.offsets", 0, histogram_size);
+
+    let pairs = if values.is_some() { "true" } else { "false" };
+
+    // When only sorting keys, the value bindings are filled with small scratch
+    // buffers that the kernel never touches.
+    let (values, temp_values) = match values {
+        Some(values) => (values, scratch.get(gpu, "sort.values", 0, size)),
+        None => (
+            scratch.get(gpu, "sort.values", 0, 4),
+            scratch.get(gpu, "sort.values", 1, 4),
+        ),
+    };
+
+    let histogram_module = include_str!("radix_histogram.wgsl");
+    let scatter_module = include_str!("radix_scatter.wgsl");
+
+    let mut buffers = [(keys, values), (temp_keys, temp_values)];
+    for pass in 0..(32 / RADIX_BITS) {
+        let [(keys_in, values_in), (keys_out, values_out)] = buffers;
+        let uniform = gpu.create_uniform(&Params {
+            len,
+            shift: pass * RADIX_BITS,
+            blocks,
+        });
+
+        gpu.kernel(histogram_module, &[])
+            .bind(&uniform)
+            .bind(&RawStorage(keys_in))
+            .bind(&RawStorage(histogram))
+            .finish()
+            .queue_dispatch(workgroups(blocks));
+
+        scan_into::<u32>(gpu, ReduceOp::Sum, histogram, offsets, RADIX * blocks);
+
+        gpu.kernel(scatter_module, &[("$PAIRS", pairs)])
+            .bind(&uniform)
+            .bind(&RawStorage(keys_in))
+            .bind(&RawStorage(keys_out))
+            .bind(&RawStorage(offsets))
+            .bind(&RawStorage(values_in))
+            .bind(&RawStorage(values_out))
+            .finish()
+            .queue_dispatch(workgroups(blocks));
+
+        buffers.swap(0, 1);
+    }
+}
@@ -38,7 +38,7 @@ impl<T> BlasBuffer<T> {
         let mut storage = StorageBuffer::new(&mut buffer);
         storage.write(&data).unwrap();
 
-        self.gpu.queue.write_buffer(&self.get(), 0, &buffer);
+        self.gpu.write_buffer(&self.get(), 0, &buffer);
     }
 }
 
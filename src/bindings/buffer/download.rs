@@ -0,0 +1,195 @@
+//! Shared logic for copying buffers back to the CPU.
+
+use std::{mem, ops::Range, thread};
+
+use bytemuck::Pod;
+
+use encase::{internal::CreateFrom, DynamicStorageBuffer, ShaderSize};
+use wgpu::{Buffer, MaintainBase, MapMode, COPY_BUFFER_ALIGNMENT};
+
+use crate::gpu::Gpu;
+
+use super::array_stride;
+
+/// Copies the bytes in `range` of the buffer to the CPU, blocking until the
+/// transfer finishes.
+pub(crate) fn download(gpu: &Gpu, buffer: &Buffer, range: Range<u64>) -> Vec<u8> {
+    read(gpu, buffer, range, <[u8]>::to_vec)
+}
+
+/// Copies the bytes in `range` of the buffer to the CPU. The callback is run
+/// on a new thread once the transfer finishes.
+pub(crate) fn download_async(
+    gpu: &Gpu,
+    buffer: &Buffer,
+    range: Range<u64>,
+    func: impl FnOnce(Vec<u8>) + Send + 'static,
+) {
+    read_async(gpu, buffer, range, <[u8]>::to_vec, func)
+}
+
+/// Copies the elements in `range` of a buffer of `T` directly into a new
+/// vector, blocking until the transfer finishes.
+pub(crate) fn download_pod<T: Pod>(gpu: &Gpu, buffer: &Buffer, range: Range<u64>) -> Vec<T> {
+    read(gpu, buffer, pod_range::<T>(range), create_pod)
+}
+
+/// Copies the elements in `range` of a buffer of `T` directly into a new
+/// vector. The callback is run on a new thread once the transfer finishes.
+pub(crate) fn download_pod_async<T: Pod + Send>(
+    gpu: &Gpu,
+    buffer: &Buffer,
+    range: Range<u64>,
+    func: impl FnOnce(Vec<T>) + Send + 'static,
+) {
+    read_async(gpu, buffer, pod_range::<T>(range), create_pod, func)
+}
+
+/// Maps the bytes in `range` of the buffer and passes them to `read`,
+/// blocking until the transfer finishes.
+fn read<R>(gpu: &Gpu, buffer: &Buffer, range: Range<u64>, read: impl FnOnce(&[u8]) -> R) -> R {
+    let (staging, aligned) = copy_to_staging(gpu, buffer, &range);
+    read_staging(gpu, staging, |data| read(trim(data, &range, &aligned)))
+}
+
+/// Maps a pooled staging buffer that has already been copied into and passes
+/// its contents to `read`, blocking until the transfer finishes. The buffer is
+/// returned to the pool afterwards.
+pub(crate) fn read_staging<R>(gpu: &Gpu, staging: Buffer, read: impl FnOnce(&[u8]) -> R) -> R {
+    let slice = staging.slice(..);
+
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    slice.map_async(MapMode::Read, move |_| tx.send(()).unwrap());
+
+    gpu.device.poll(MaintainBase::Wait);
+    rx.recv().unwrap();
+
+    let out = read(&slice.get_mapped_range());
+    gpu.staging.give(staging);
+    out
+}
+
+/// Maps the bytes in `range` of the buffer and passes them to `read` once the
+/// transfer finishes. The result is handed to `func` on a new thread.
+fn read_async<R: Send + 'static>(
+    gpu: &Gpu,
+    buffer: &Buffer,
+    range: Range<u64>,
+    read: impl FnOnce(&[u8]) -> R + Send + 'static,
+    func: impl FnOnce(R) + Send + 'static,
+) {
+    let (staging, aligned) = copy_to_staging(gpu, buffer, &range);
+    read_staging_async(
+        gpu,
+        staging,
+        move |data| read(trim(data, &range, &aligned)),
+        func,
+    );
+}
+
+/// Maps a pooled staging buffer that has already been copied into and passes
+/// its contents to `read` once the transfer finishes. The result is handed to
+/// `func` on a new thread, and the buffer is returned to the pool.
+pub(crate) fn read_staging_async<R: Send + 'static>(
+    gpu: &Gpu,
+    staging: Buffer,
+    read: impl FnOnce(&[u8]) -> R + Send + 'static,
+    func: impl FnOnce(R) + Send + 'static,
+) {
+    let pool = gpu.staging.clone();
+
+    let mapped = staging.clone();
+    staging.slice(..).map_async(MapMode::Read, move |_| {
+        let out = read(&mapped.slice(..).get_mapped_range());
+        pool.give(mapped);
+
+        thread::spawn(move || func(out));
+    });
+}
+
+/// The bytes holding the elements in `range` of a WGSL array of `Elem`,
+/// clamped to the end of the buffer.
+pub(crate) fn array_range<Elem: ShaderSize>(range: Range<u64>, size: u64) -> Range<u64> {
+    let stride = array_stride::<Elem>();
+    assert!(
+        range.start <= range.end && range.end * stride <= size.next_multiple_of(stride),
+        "Elements {range:?} are out of bounds"
+    );
+
+    (range.start * stride)..(range.end * stride).min(size)
+}
+
+/// The bytes holding the element at `index` of a WGSL array of `Elem`.
+pub(crate) fn element_range<Elem: ShaderSize>(index: u64, size: u64) -> Range<u64> {
+    let start = index * array_stride::<Elem>();
+    let end = start + Elem::SHADER_SIZE.get();
+    assert!(end <= size, "Element {index} is out of bounds");
+
+    start..end
+}
+
+/// The bytes holding the elements in `range` of a tightly packed array of `T`.
+fn pod_range<T: Pod>(range: Range<u64>) -> Range<u64> {
+    let size = mem::size_of::<T>() as u64;
+    (range.start * size)..(range.end * size)
+}
+
+fn create_pod<T: Pod>(bytes: &[u8]) -> Vec<T> {
+    let mut out = vec![T::zeroed(); bytes.len() / mem::size_of::<T>()];
+    bytemuck::cast_slice_mut(&mut out).copy_from_slice(bytes);
+    out
+}
+
+/// Decodes a WGSL array of `Elem`. The final element is allowed to be missing
+/// its trailing padding.
+pub(crate) fn create_array<Elem>(mut bytes: Vec<u8>) -> Vec<Elem>
+where
+    Elem: ShaderSize + CreateFrom,
+{
+    let stride = array_stride::<Elem>() as usize;
+    if bytes.is_empty() {
+        return Vec::new();
+    }
+
+    bytes.resize(bytes.len().next_multiple_of(stride), 0);
+    DynamicStorageBuffer::new(bytes).create().unwrap()
+}
+
+/// Copies `range` of the buffer into a pooled mappable buffer. As copies must be
+/// aligned to [`COPY_BUFFER_ALIGNMENT`], the range that was actually copied is
+/// also returned.
+fn copy_to_staging(gpu: &Gpu, buffer: &Buffer, range: &Range<u64>) -> (Buffer, Range<u64>) {
+    let aligned = align(range, buffer.size());
+    let staging = gpu.staging.take(&gpu.device, staging_size(&aligned));
+
+    gpu.immediate_dispatch(|encoder| {
+        encoder.copy_buffer_to_buffer(
+            buffer,
+            aligned.start,
+            &staging,
+            0,
+            aligned.end - aligned.start,
+        );
+    });
+
+    (staging, aligned)
+}
+
+/// Widens `range` to [`COPY_BUFFER_ALIGNMENT`], without going past the end of
+/// a buffer of `size` bytes.
+pub(crate) fn align(range: &Range<u64>, size: u64) -> Range<u64> {
+    let start = range.start - range.start % COPY_BUFFER_ALIGNMENT;
+    let end = range.end.next_multiple_of(COPY_BUFFER_ALIGNMENT).min(size);
+    start..end
+}
+
+/// The size of the staging buffer needed to copy an aligned range.
+pub(crate) fn staging_size(aligned: &Range<u64>) -> u64 {
+    (aligned.end - aligned.start).max(COPY_BUFFER_ALIGNMENT)
+}
+
+/// The bytes of `range` within data copied from `aligned`.
+pub(crate) fn trim<'a>(data: &'a [u8], range: &Range<u64>, aligned: &Range<u64>) -> &'a [u8] {
+    let start = (range.start - aligned.start) as usize;
+    &data[start..start + (range.end - range.start) as usize]
+}
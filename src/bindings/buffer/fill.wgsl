@@ -0,0 +1,18 @@
+// Repeats `pattern` over every word of `data`.
+
+@group(0) @binding(0) var<uniform> words: u32;
+@group(0) @binding(1) var<storage, read_write> data: array<u32>;
+@group(0) @binding(2) var<storage, read_write> pattern: array<u32>;
+
+@compute
+@workgroup_size(256, 1, 1)
+fn main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) local: u32
+) {
+    let index = (group.x + group.y * groups.x) * 256u + local;
+    if index < words {
+        data[index] = pattern[index % arrayLength(&pattern)];
+    }
+}
@@ -1,7 +1,10 @@
+use std::marker::PhantomData;
+
+use bytemuck::Pod;
 use parking_lot::MappedRwLockReadGuard;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    BindingType, Buffer, BufferUsages,
+    BindingType, Buffer, BufferUsages, IndexFormat, COPY_BUFFER_ALIGNMENT,
 };
 
 use crate::{
@@ -12,21 +15,40 @@ use crate::{
 
 use super::BufferBinding;
 
-/// Represents the indices of a mesh for rendering.
-pub struct IndexBuffer {
+/// The integer types that can be used as indices.
+pub trait IndexType: Pod {
+    fn as_format() -> IndexFormat;
+}
+
+impl IndexType for u16 {
+    fn as_format() -> IndexFormat {
+        IndexFormat::Uint16
+    }
+}
+
+impl IndexType for u32 {
+    fn as_format() -> IndexFormat {
+        IndexFormat::Uint32
+    }
+}
+
+/// Represents the indices of a mesh for rendering. Indices can be either `u16`
+/// or `u32`.
+pub struct IndexBuffer<T = u32> {
     gpu: Gpu,
     buffer: BufferId,
+    _type: PhantomData<T>,
 }
 
-impl IndexBuffer {
+impl<T: IndexType> IndexBuffer<T> {
     pub(crate) fn get(&self) -> MappedRwLockReadGuard<Buffer> {
         MappedRwLockReadGuard::map(self.gpu.binding_manager.get_resource(self.buffer), |x| {
             x.expect_buffer()
         })
     }
 
-    pub fn upload(&self, data: &[u32]) {
-        let buffer = bytemuck::cast_slice(data);
+    pub fn upload(&self, data: &[T]) {
+        let buffer = &index_bytes(data);
 
         let this = self.get();
         if buffer.len() as u64 > this.size() {
@@ -34,24 +56,24 @@ impl IndexBuffer {
             let replacement = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
                 label: None,
                 contents: buffer,
-                usage: BufferUsages::COPY_DST | BufferUsages::INDEX,
+                usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::INDEX,
             });
 
             let binding_manager = &self.gpu.binding_manager;
             binding_manager.add_resource(self.buffer, replacement);
             binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
         } else {
-            self.gpu.queue.write_buffer(&this, 0, buffer);
+            self.gpu.write_buffer(&this, 0, buffer);
         }
     }
 }
 
 impl Gpu {
-    pub fn create_index(&self, data: &[u32]) -> IndexBuffer {
+    pub fn create_index<T: IndexType>(&self, data: &[T]) -> IndexBuffer<T> {
         let id = BufferId::new();
         let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            usage: BufferUsages::COPY_DST | BufferUsages::INDEX,
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::INDEX,
             contents: bytemuck::cast_slice(data),
         });
 
@@ -59,15 +81,17 @@ impl Gpu {
         IndexBuffer {
             gpu: self.clone(),
             buffer: id,
+            _type: PhantomData,
         }
     }
 
-    pub fn create_index_empty(&self, size: usize) -> IndexBuffer {
+    pub fn create_index_empty<T: IndexType>(&self, size: usize) -> IndexBuffer<T> {
         let id = BufferId::new();
         let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: (size * std::mem::size_of::<u32>()) as u64,
-            usage: BufferUsages::COPY_DST | BufferUsages::INDEX,
+            size: ((size * std::mem::size_of::<T>()) as u64)
+                .next_multiple_of(COPY_BUFFER_ALIGNMENT),
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::INDEX,
             mapped_at_creation: false,
         });
 
@@ -75,17 +99,29 @@ impl Gpu {
         IndexBuffer {
             gpu: self.clone(),
             buffer: id,
+            _type: PhantomData,
         }
     }
 }
 
-impl BufferBinding for IndexBuffer {
+/// Writes to buffers must be a multiple of four bytes, which an odd number of
+/// `u16` indices isn't.
+fn index_bytes<T: IndexType>(data: &[T]) -> Vec<u8> {
+    let mut bytes = bytemuck::cast_slice(data).to_vec();
+    bytes.resize(
+        bytes.len().next_multiple_of(COPY_BUFFER_ALIGNMENT as usize),
+        0,
+    );
+    bytes
+}
+
+impl<T> BufferBinding for IndexBuffer<T> {
     fn get_id(&self) -> BufferId {
         self.buffer
     }
 }
 
-impl Bindable for IndexBuffer {
+impl<T> Bindable for IndexBuffer<T> {
     fn resource_id(&self) -> BindableResourceId {
         BindableResourceId::Buffer(self.buffer)
     }
@@ -99,7 +135,7 @@ impl Bindable for IndexBuffer {
     }
 }
 
-impl Drop for IndexBuffer {
+impl<T> Drop for IndexBuffer<T> {
     fn drop(&mut self) {
         self.gpu.binding_manager.remove_resource(self.buffer);
     }
@@ -1,21 +1,161 @@
 //! Buffers represent GPU memory allocations that can be bound to render and compute pipelines.
 
-use crate::misc::ids::BufferId;
+use std::ops::Range;
+
+use encase::ShaderSize;
+use wgpu::{BindingType, BufferBindingType, COPY_BUFFER_ALIGNMENT};
+
+use crate::{gpu::Gpu, misc::ids::BufferId};
+
+use super::{Bindable, BindableResourceId};
 
 mod blas;
+pub(crate) mod download;
 mod index;
 pub mod mutability;
+mod npy;
+mod ping_pong;
+mod pod_storage;
+mod pod_vertex;
+mod readback;
 mod storage;
+mod typed;
 mod uniform;
+mod vec;
 mod vertex;
 
 pub use blas::BlasBuffer;
-pub use index::IndexBuffer;
+pub use index::{IndexBuffer, IndexType};
+pub use npy::{NpyElement, NpyScalar};
+pub use ping_pong::{PingPong, PingPongBinding};
+pub use pod_storage::PodStorageBuffer;
+pub use pod_vertex::PodVertexBuffer;
+pub use readback::ReadbackRing;
 pub use storage::StorageBuffer;
+pub use typed::{BufferBuilder, BufferRole, TypedBuffer};
 pub use uniform::UniformBuffer;
+pub use vec::GpuVec;
 pub use vertex::VertexBuffer;
 
 /// Represents a buffer that can be bound to a pipline.
 pub trait BufferBinding {
     fn get_id(&self) -> BufferId;
 }
+
+/// A buffer that can be used for the vertices or instances of a draw call.
+pub trait VertexBinding: BufferBinding {}
+
+/// Binds any buffer as a read-write storage buffer. Used by tufa's built-in
+/// kernels so they don't depend on the mutability of the buffers they are given.
+pub(crate) struct RawStorage(pub BufferId);
+
+impl Bindable for RawStorage {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Buffer(self.0)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl Gpu {
+    /// Copies the bytes in `range` of `source` into `destination`, starting at
+    /// `offset` bytes in. Both buffers can be of any kind, but the range and
+    /// offset must be multiples of four.
+    pub fn copy_buffer(
+        &self,
+        source: &impl BufferBinding,
+        range: Range<u64>,
+        destination: &impl BufferBinding,
+        offset: u64,
+    ) {
+        self.copy_buffer_inner(source, range, destination, offset, true);
+    }
+
+    /// Queues a [`Gpu::copy_buffer`] with the next compute dispach, render
+    /// pass, or call to [`Gpu::flush_dispatch_queue`].
+    pub fn queue_copy_buffer(
+        &self,
+        source: &impl BufferBinding,
+        range: Range<u64>,
+        destination: &impl BufferBinding,
+        offset: u64,
+    ) {
+        self.copy_buffer_inner(source, range, destination, offset, false);
+    }
+
+    fn copy_buffer_inner(
+        &self,
+        source: &impl BufferBinding,
+        range: Range<u64>,
+        destination: &impl BufferBinding,
+        offset: u64,
+        immediate: bool,
+    ) {
+        let (source, destination) = self.checked_copy(source, &range, destination, offset);
+        let size = range.end - range.start;
+        self.dispach(
+            |encoder| {
+                encoder.copy_buffer_to_buffer(&source, range.start, &destination, offset, size)
+            },
+            immediate,
+        );
+    }
+
+    /// Checks that a copy of `range` from `source` to `offset` in
+    /// `destination` is valid, and returns both underlying buffers.
+    pub(crate) fn checked_copy(
+        &self,
+        source: &impl BufferBinding,
+        range: &Range<u64>,
+        destination: &impl BufferBinding,
+        offset: u64,
+    ) -> (wgpu::Buffer, wgpu::Buffer) {
+        assert!(range.start <= range.end, "Copy range {range:?} is reversed");
+        assert!(
+            source.get_id() != destination.get_id(),
+            "Can't copy a buffer into itself"
+        );
+        assert!(
+            range.start.is_multiple_of(COPY_BUFFER_ALIGNMENT)
+                && range.end.is_multiple_of(COPY_BUFFER_ALIGNMENT)
+                && offset.is_multiple_of(COPY_BUFFER_ALIGNMENT),
+            "Copy range {range:?} and offset {offset} must be multiples of {COPY_BUFFER_ALIGNMENT}"
+        );
+
+        // Each buffer is cloned out of the manager so only one lock is held at
+        // a time.
+        let manager = &self.binding_manager;
+        let source = manager
+            .get_resource(source.get_id())
+            .expect_buffer()
+            .clone();
+        let destination = (manager.get_resource(destination.get_id()))
+            .expect_buffer()
+            .clone();
+
+        let size = range.end - range.start;
+        assert!(
+            range.end <= source.size(),
+            "Copy of bytes {range:?} is out of bounds for a source of {} bytes",
+            source.size()
+        );
+        assert!(
+            offset + size <= destination.size(),
+            "Copy of {size} bytes to offset {offset} is out of bounds for a destination of {} bytes",
+            destination.size()
+        );
+
+        (source, destination)
+    }
+}
+
+/// The distance in bytes between elements of a WGSL array of `T`.
+pub(crate) const fn array_stride<T: ShaderSize>() -> u64 {
+    T::METADATA.alignment().round_up(T::SHADER_SIZE.get())
+}
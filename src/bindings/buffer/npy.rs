@@ -0,0 +1,459 @@
+//! Reading and writing NumPy `.npy` files, for moving data between storage
+//! buffers and Python.
+//!
+//! Buffers of scalars map to one dimensional arrays, and buffers of nalgebra
+//! vectors map to arrays of shape `(n, N)`. As WGSL has no 8 bit or 64 bit
+//! types, `u8` and `i8` arrays can be loaded into `u32` and `i32` buffers, and
+//! `f64` arrays can be loaded into `f32` buffers, converting each element.
+//!
+//! ```python
+//! positions = np.load("positions.npy")  # shape (n, 4), dtype float32
+//! ```
+
+use std::{
+    any,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use bytemuck::Pod;
+use encase::{
+    internal::{CreateFrom, WriteInto},
+    ShaderSize, ShaderType,
+};
+use nalgebra::{SVector, Scalar};
+
+use crate::{bindings::buffer::mutability::Mutability, gpu::Gpu};
+
+use super::StorageBuffer;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// A scalar type that can be stored in a `.npy` file.
+pub trait NpyScalar: Pod + Scalar {
+    /// The NumPy dtype this type is saved as.
+    const DESCR: &'static str;
+
+    /// Converts little endian data of the dtype `descr` into this type,
+    /// returning `None` if it can't be.
+    fn decode(descr: &str, data: &[u8]) -> Option<Vec<Self>>;
+}
+
+/// A buffer element that can be stored in a `.npy` file.
+pub trait NpyElement: ShaderSize + WriteInto + CreateFrom {
+    type Scalar: NpyScalar;
+
+    /// The size of the array's second axis, or `None` if the array is one
+    /// dimensional.
+    const COLUMNS: Option<usize>;
+
+    fn scalars(&self) -> &[Self::Scalar];
+    fn from_scalars(scalars: &[Self::Scalar]) -> Self;
+}
+
+macro_rules! npy_scalars {
+    ($($ty:ty => $descr:literal $(, $from:ty => $from_descr:literal)*;)*) => {
+        $(
+            impl NpyScalar for $ty {
+                const DESCR: &'static str = $descr;
+
+                fn decode(descr: &str, data: &[u8]) -> Option<Vec<Self>> {
+                    match descr {
+                        $descr => Some(bytemuck::pod_collect_to_vec(data)),
+                        $(
+                            $from_descr => Some(
+                                bytemuck::pod_collect_to_vec::<u8, $from>(data)
+                                    .into_iter()
+                                    .map(|x| x as $ty)
+                                    .collect(),
+                            ),
+                        )*
+                        _ => None,
+                    }
+                }
+            }
+
+            impl NpyElement for $ty {
+                type Scalar = $ty;
+                const COLUMNS: Option<usize> = None;
+
+                fn scalars(&self) -> &[Self::Scalar] {
+                    std::slice::from_ref(self)
+                }
+
+                fn from_scalars(scalars: &[Self::Scalar]) -> Self {
+                    scalars[0]
+                }
+            }
+        )*
+    };
+}
+
+npy_scalars! {
+    f32 => "<f4", f64 => "<f8";
+    u32 => "<u4", u8 => "|u1", u16 => "<u2";
+    i32 => "<i4", i8 => "|i1", i16 => "<i2";
+}
+
+impl<S: NpyScalar, const N: usize> NpyElement for SVector<S, N>
+where
+    Self: ShaderSize + WriteInto + CreateFrom,
+{
+    type Scalar = S;
+    const COLUMNS: Option<usize> = Some(N);
+
+    fn scalars(&self) -> &[Self::Scalar] {
+        self.as_slice()
+    }
+
+    fn from_scalars(scalars: &[Self::Scalar]) -> Self {
+        Self::from_column_slice(scalars)
+    }
+}
+
+impl<Elem, Mut: Mutability> StorageBuffer<Vec<Elem>, Mut>
+where
+    Elem: NpyElement + ShaderType,
+{
+    /// Downloads the buffer and writes it to a `.npy` file. Only the
+    /// [`len`](Self::len) elements written by the last upload are saved, not
+    /// any spare capacity after them.
+    pub fn save_npy(&self, path: impl AsRef<Path>) -> Result<()> {
+        let data = self.download_range(0..self.len());
+        let shape = match Elem::COLUMNS {
+            Some(columns) => format!("({}, {columns})", data.len()),
+            None => format!("({},)", data.len()),
+        };
+
+        // The header is padded so the data starts at a multiple of 64 bytes.
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}",
+            Elem::Scalar::DESCR
+        );
+        let len = (MAGIC.len() + 4 + header.len() + 1).next_multiple_of(64) - MAGIC.len() - 4;
+        header.extend(std::iter::repeat_n(' ', len - header.len() - 1));
+        header.push('\n');
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[1, 0])?;
+        file.write_all(&(header.len() as u16).to_le_bytes())?;
+        file.write_all(header.as_bytes())?;
+        for element in data.iter() {
+            file.write_all(bytemuck::cast_slice(element.scalars()))?;
+        }
+
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl Gpu {
+    /// Creates a storage buffer from a `.npy` file. The array must be C ordered,
+    /// with a shape and dtype matching `T`.
+    pub fn load_npy<T, Mut: Mutability>(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<StorageBuffer<Vec<T>, Mut>>
+    where
+        T: NpyElement + ShaderType,
+    {
+        let path = path.as_ref();
+        let mut file = BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        );
+        let file_len = file.get_ref().metadata()?.len();
+        let header = Header::read(&mut file)
+            .with_context(|| format!("Invalid .npy header in {}", path.display()))?;
+        let rows = rows::<T>(&header.shape)?;
+
+        let count = rows
+            .checked_mul(T::COLUMNS.unwrap_or(1))
+            .with_context(|| format!("{} has too many elements", path.display()))?;
+        let size = header
+            .data_size(count, file_len)
+            .with_context(|| format!("Invalid .npy data in {}", path.display()))?;
+
+        let mut data = vec![0; size];
+        file.read_exact(&mut data)?;
+
+        let Some(scalars) = T::Scalar::decode(&header.descr, &data) else {
+            bail!(
+                "Can't load an array of dtype '{}' into a buffer of {}",
+                header.descr,
+                any::type_name::<T>()
+            );
+        };
+
+        let elements = scalars
+            .chunks(T::COLUMNS.unwrap_or(1))
+            .map(T::from_scalars)
+            .collect::<Vec<_>>();
+        Ok(self.create_storage(&elements))
+    }
+}
+
+/// The number of rows in an array of `shape`, if it can be loaded as `T`.
+fn rows<T: NpyElement>(shape: &[usize]) -> Result<usize> {
+    Ok(match (T::COLUMNS, shape) {
+        (None, [rows]) => *rows,
+        (Some(columns), [rows, x]) if *x == columns => *rows,
+        (None, shape) => bail!(
+            "Expected an array of shape (n,) for {}, but found {}",
+            any::type_name::<T>(),
+            tuple(shape)
+        ),
+        (Some(columns), shape) => bail!(
+            "Expected an array of shape (n, {columns}) for {}, but found {}",
+            any::type_name::<T>(),
+            tuple(shape)
+        ),
+    })
+}
+
+struct Header {
+    descr: String,
+    shape: Vec<usize>,
+    /// The number of bytes before the data, including the magic and version.
+    len: u64,
+}
+
+impl Header {
+    fn read(input: &mut impl Read) -> Result<Self> {
+        let mut magic = [0; 6];
+        input.read_exact(&mut magic)?;
+        ensure!(&magic == MAGIC, "Not a .npy file");
+
+        let mut version = [0; 2];
+        input.read_exact(&mut version)?;
+        let len = match version[0] {
+            1 => {
+                let mut len = [0; 2];
+                input.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0; 4];
+                input.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            major => bail!("Unsupported .npy version {major}.{}", version[1]),
+        };
+
+        let prefix = MAGIC.len() + version.len() + if version[0] == 1 { 2 } else { 4 };
+        let mut header = vec![0; len];
+        input.read_exact(&mut header)?;
+        let header = String::from_utf8(header)?;
+
+        let descr = field(&header, "descr")?;
+        let quote = descr.chars().next().context("Missing dtype")?;
+        let descr = descr[1..]
+            .split(quote)
+            .next()
+            .context("Unterminated dtype")?;
+
+        let shape = field(&header, "shape")?;
+        let shape = shape
+            .strip_prefix('(')
+            .and_then(|x| x.split(')').next())
+            .context("Shape must be a tuple")?
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .context("Invalid shape")?;
+
+        ensure!(
+            !field(&header, "fortran_order")?.starts_with("True"),
+            "Fortran ordered arrays aren't supported, save the array with `np.ascontiguousarray`"
+        );
+
+        Ok(Self {
+            // Native byte order is always little endian on platforms wgpu
+            // supports.
+            descr: descr.replacen('=', "<", 1),
+            shape,
+            len: (prefix + len) as u64,
+        })
+    }
+
+    /// The size in bytes of `count` scalars, checking that it's exactly what
+    /// follows the header in a file of `file_len` bytes.
+    fn data_size(&self, count: usize, file_len: u64) -> Result<usize> {
+        let size = count
+            .checked_mul(self.item_size()?)
+            .context("Array is too large")?;
+        let available = file_len.saturating_sub(self.len);
+        if size as u64 != available {
+            bail!(
+                "Shape {} needs {size} bytes of data, but the file has {available}",
+                tuple(&self.shape)
+            );
+        }
+
+        Ok(size)
+    }
+
+    /// The size in bytes of each scalar, taken from the end of the dtype.
+    fn item_size(&self) -> Result<usize> {
+        ensure!(
+            !self.descr.starts_with('>'),
+            "Big endian arrays aren't supported"
+        );
+        self.descr[1..]
+            .trim_start_matches(char::is_alphabetic)
+            .parse()
+            .with_context(|| format!("Unsupported dtype '{}'", self.descr))
+    }
+}
+
+/// Formats a shape like Python would.
+fn tuple(shape: &[usize]) -> String {
+    match shape {
+        [x] => format!("({x},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The text following `key` in the header's dictionary.
+fn field<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    let start = [format!("'{key}':"), format!("\"{key}\":")]
+        .iter()
+        .find_map(|x| header.find(x.as_str()).map(|i| i + x.len()))
+        .with_context(|| format!("Missing '{key}'"))?;
+    Ok(header[start..].trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector4;
+
+    use super::*;
+
+    /// A file header as written by `np.save`, padded to a multiple of 64 bytes.
+    fn npy(major: u8, dict: &str) -> Vec<u8> {
+        let prefix = MAGIC.len() + 2 + if major == 1 { 2 } else { 4 };
+        let len = (prefix + dict.len() + 1).next_multiple_of(64) - prefix;
+        let header = format!("{dict:<0$}\n", len - 1);
+
+        let mut out = MAGIC.to_vec();
+        out.extend([major, 0]);
+        match major {
+            1 => out.extend((len as u16).to_le_bytes()),
+            _ => out.extend((len as u32).to_le_bytes()),
+        }
+        out.extend(header.as_bytes());
+        out
+    }
+
+    fn read(major: u8, dict: &str) -> Result<Header> {
+        Header::read(&mut npy(major, dict).as_slice())
+    }
+
+    #[test]
+    fn version_1() {
+        let header = read(
+            1,
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 4), }",
+        )
+        .unwrap();
+        assert_eq!(header.descr, "<f4");
+        assert_eq!(header.shape, [3, 4]);
+        assert_eq!(header.item_size().unwrap(), 4);
+        assert_eq!(rows::<Vector4<f32>>(&header.shape).unwrap(), 3);
+    }
+
+    #[test]
+    fn version_2() {
+        let header = read(
+            2,
+            "{'descr': '<i4', 'fortran_order': False, 'shape': (5,), }",
+        )
+        .unwrap();
+        assert_eq!(header.descr, "<i4");
+        assert_eq!(header.shape, [5]);
+    }
+
+    #[test]
+    fn fortran_order() {
+        let error = read(
+            1,
+            "{'descr': '<f4', 'fortran_order': True, 'shape': (2, 3), }",
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("Fortran"));
+    }
+
+    #[test]
+    fn bytes() {
+        let header = read(
+            1,
+            "{'descr': '|u1', 'fortran_order': False, 'shape': (3,), }",
+        )
+        .unwrap();
+        assert_eq!(header.item_size().unwrap(), 1);
+        assert_eq!(
+            u32::decode(&header.descr, &[1, 2, 255]).unwrap(),
+            [1, 2, 255]
+        );
+    }
+
+    #[test]
+    fn doubles() {
+        let header = read(
+            1,
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (2,), }",
+        )
+        .unwrap();
+        assert_eq!(header.item_size().unwrap(), 8);
+
+        let data = bytemuck::cast_slice(&[1.5f64, -2.0]).to_vec();
+        assert_eq!(f32::decode(&header.descr, &data).unwrap(), [1.5, -2.0]);
+    }
+
+    #[test]
+    fn scalar_shape() {
+        let header = read(1, "{'descr': '<f4', 'fortran_order': False, 'shape': (), }").unwrap();
+        assert!(header.shape.is_empty());
+        assert!(rows::<f32>(&header.shape).is_err());
+    }
+
+    #[test]
+    fn data_size() {
+        let file = npy(
+            1,
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 4), }",
+        );
+        let header = Header::read(&mut file.as_slice()).unwrap();
+        assert_eq!(header.len, file.len() as u64);
+
+        let len = file.len() as u64;
+        assert_eq!(header.data_size(12, len + 48).unwrap(), 48);
+        assert!(header.data_size(12, len + 44).is_err());
+        assert!(header.data_size(12, len + 52).is_err());
+        assert!(header.data_size(usize::MAX, len + 48).is_err());
+    }
+
+    #[test]
+    fn trailing_comma() {
+        let header = read(
+            1,
+            "{'descr': '<u4', 'fortran_order': False, 'shape': (7,), }",
+        )
+        .unwrap();
+        assert_eq!(header.shape, [7]);
+        assert_eq!(rows::<u32>(&header.shape).unwrap(), 7);
+        assert!(rows::<Vector4<u32>>(&header.shape).is_err());
+    }
+}
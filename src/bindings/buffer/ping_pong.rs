@@ -0,0 +1,138 @@
+use encase::{
+    internal::{CreateFrom, WriteInto},
+    ShaderType,
+};
+use wgpu::BindingType;
+
+use crate::{
+    bindings::{
+        buffer::mutability::Mutable,
+        wgsl::{self, WgslType},
+        Bindable, BindableResourceId, Bindings,
+    },
+    gpu::Gpu,
+    misc::ids::PingPongId,
+};
+
+use super::StorageBuffer;
+
+/// A pair of storage buffers for simulations that compute the next state from
+/// the current one. The pair is bound as a read-only `current` buffer followed
+/// by a read-write `next` buffer:
+///
+/// ```wgsl
+/// @group(0) @binding(0) var<storage, read> current: array<f32>;
+/// @group(0) @binding(1) var<storage, read_write> next: array<f32>;
+/// ```
+///
+/// After each step, calling [`PingPong::swap`] exchanges the buffers, so the
+/// next dispatch reads what the last one wrote. Pipelines keep a bind group
+/// for each arrangement, so swapping doesn't rebuild anything.
+pub struct PingPong<T> {
+    gpu: Gpu,
+    id: PingPongId,
+    buffers: [StorageBuffer<T, Mutable>; 2],
+
+    read: PingPongBinding,
+    write: PingPongBinding,
+}
+
+/// One side of a [`PingPong`] pair, for binding the buffers individually.
+pub struct PingPongBinding {
+    id: PingPongId,
+    write: bool,
+}
+
+impl<T: ShaderType + WriteInto + CreateFrom> PingPong<T> {
+    /// Exchanges the current and next buffers.
+    pub fn swap(&self) {
+        self.gpu.binding_manager.swap_ping_pong(self.id);
+    }
+
+    /// The buffer holding the latest state, which is bound as `current`.
+    pub fn current(&self) -> &StorageBuffer<T, Mutable> {
+        &self.buffers[self.swapped() as usize]
+    }
+
+    /// The buffer that will be written to, which is bound as `next`.
+    pub fn next(&self) -> &StorageBuffer<T, Mutable> {
+        &self.buffers[!self.swapped() as usize]
+    }
+
+    /// The read-only side of the pair. Always refers to the current buffer,
+    /// even after swapping.
+    pub fn read(&self) -> &PingPongBinding {
+        &self.read
+    }
+
+    /// The read-write side of the pair. Always refers to the next buffer, even
+    /// after swapping.
+    pub fn write(&self) -> &PingPongBinding {
+        &self.write
+    }
+
+    fn swapped(&self) -> bool {
+        let current = self.gpu.binding_manager.resolve(self.read.resource_id());
+        current != BindableResourceId::Buffer(self.buffers[0].buffer)
+    }
+}
+
+impl Gpu {
+    /// Creates a ping-pong pair with both buffers holding `data`.
+    pub fn create_ping_pong<T>(&self, data: &T) -> PingPong<T>
+    where
+        T: ShaderType + WriteInto + CreateFrom,
+    {
+        let buffers = [self.create_storage(data), self.create_storage(data)];
+
+        let id = PingPongId::new();
+        self.binding_manager
+            .add_ping_pong(id, [buffers[0].buffer, buffers[1].buffer]);
+
+        PingPong {
+            gpu: self.clone(),
+            id,
+            buffers,
+
+            read: PingPongBinding { id, write: false },
+            write: PingPongBinding { id, write: true },
+        }
+    }
+}
+
+impl Bindable for PingPongBinding {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::PingPong {
+            id: self.id,
+            write: self.write,
+        }
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage {
+                read_only: !self.write,
+            },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl<T: WgslType> Bindings for PingPong<T> {
+    fn bindings(&self) -> Vec<&dyn Bindable> {
+        vec![&self.read, &self.write]
+    }
+
+    fn wgsl(&self) -> String {
+        let ty = T::wgsl_type();
+        wgsl::declaration(0, 0, "current", &self.read, &ty)
+            + &wgsl::declaration(0, 1, "next", &self.write, &ty)
+    }
+}
+
+impl<T> Drop for PingPong<T> {
+    fn drop(&mut self) {
+        self.gpu.binding_manager.remove_ping_pong(self.id);
+    }
+}
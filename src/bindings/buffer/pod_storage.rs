@@ -0,0 +1,257 @@
+use std::{
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bytemuck::Pod;
+use encase::ShaderSize;
+use parking_lot::MappedRwLockReadGuard;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindingType, Buffer, BufferDescriptor, BufferUsages,
+};
+
+use crate::{
+    bindings::{
+        buffer::mutability::{Immutable, Mutability, Mutable},
+        Bindable, BindableResourceId,
+    },
+    gpu::Gpu,
+    misc::ids::BufferId,
+};
+
+use super::{array_stride, download, BufferBinding};
+
+/// A storage buffer holding a WGSL `array<T>` of plain old data. Unlike
+/// [`StorageBuffer`](super::StorageBuffer), data is copied to and from the GPU
+/// as is, without going through encase.
+///
+/// `T` must be a scalar, vector, matrix, or array of them that encase can
+/// copy without padding, and its size in Rust must match its stride in a WGSL
+/// array. Both are checked at compile time. The check goes by `T`'s
+/// [`ShaderType`] layout, so while `[f32; 4]` can be read as a `vec4<f32>`,
+/// `[f32; 3]` is only valid as an `array<f32, 3>` since a `vec3<f32>` is
+/// padded to 16 bytes. Structs aren't accepted, as the offsets of their fields
+/// can't be checked; use a [`StorageBuffer`](super::StorageBuffer) for them.
+///
+/// [`ShaderType`]: encase::ShaderType
+pub struct PodStorageBuffer<T, Mut: Mutability> {
+    gpu: Gpu,
+    buffer: BufferId,
+    /// The number of elements holding data, set by uploads and grown by
+    /// partial writes. This may be less than the allocation can hold.
+    len: AtomicU64,
+
+    _type: PhantomData<T>,
+    _mut: PhantomData<Mut>,
+}
+
+impl<T: Pod, Mut: Mutability> PodStorageBuffer<T, Mut> {
+    pub(crate) fn get(&self) -> MappedRwLockReadGuard<Buffer> {
+        MappedRwLockReadGuard::map(self.gpu.binding_manager.get_resource(self.buffer), |x| {
+            x.expect_buffer()
+        })
+    }
+
+    /// The number of elements the buffer was created with or last uploaded.
+    /// As uploads only grow the allocation, it may hold more elements than
+    /// this. Writes with [`upload_range`](Self::upload_range) extend the
+    /// length if they write past it.
+    pub fn len(&self) -> u64 {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the buffer holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Uploads data into the buffer, reallocating it if it is too small.
+    pub fn upload(&self, data: &[T]) {
+        let bytes = bytemuck::cast_slice(data);
+        self.len.store(data.len() as u64, Ordering::Relaxed);
+
+        let buffer = self.get();
+        if bytes.len() as u64 > buffer.size() {
+            drop(buffer);
+            let replacement = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: bytes,
+                usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::STORAGE,
+            });
+
+            let binding_manager = &self.gpu.binding_manager;
+            binding_manager.add_resource(self.buffer, replacement);
+            binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
+        } else {
+            self.gpu.write_buffer(&buffer, 0, bytes);
+        }
+    }
+
+    /// Uploads `data` into the buffer, starting at the element with index
+    /// `offset`. The rest of the buffer is left untouched.
+    pub fn upload_range(&self, offset: u64, data: &[T]) {
+        let buffer = self.get();
+        let bytes = bytemuck::cast_slice(data);
+        let end = offset + data.len() as u64;
+        let offset = offset * mem::size_of::<T>() as u64;
+        assert!(
+            offset + bytes.len() as u64 <= buffer.size(),
+            "Write of {} elements is out of bounds",
+            data.len()
+        );
+
+        self.gpu.write_buffer(&buffer, offset, bytes);
+        self.len.fetch_max(end, Ordering::Relaxed);
+    }
+
+    /// Downloads the buffer from the GPU in a blocking manner.
+    pub fn download(&self) -> Vec<T> {
+        self.download_range(0..self.len())
+    }
+
+    /// Requests the download of the buffer. The provided callback will be
+    /// executed once the transfer finishes.
+    pub fn download_async(&self, func: impl FnOnce(Vec<T>) + Send + 'static)
+    where
+        T: Send,
+    {
+        self.download_range_async(0..self.len(), func);
+    }
+
+    /// Downloads the elements in `range` from the GPU in a blocking manner.
+    pub fn download_range(&self, range: Range<u64>) -> Vec<T> {
+        self.check_range(&range);
+        download::download_pod(&self.gpu, &self.get(), range)
+    }
+
+    /// Requests the download of the elements in `range`. The provided callback
+    /// will be executed once the transfer finishes.
+    pub fn download_range_async(
+        &self,
+        range: Range<u64>,
+        func: impl FnOnce(Vec<T>) + Send + 'static,
+    ) where
+        T: Send,
+    {
+        self.check_range(&range);
+        download::download_pod_async(&self.gpu, &self.get(), range, func);
+    }
+
+    /// Downloads the element at `index` from the GPU in a blocking manner.
+    pub fn download_element(&self, index: u64) -> T {
+        self.download_range(index..index + 1)[0]
+    }
+
+    fn check_range(&self, range: &Range<u64>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "Elements {range:?} are out of bounds"
+        );
+    }
+}
+
+impl Gpu {
+    /// Creates a storage buffer that uploads `data` without any conversion.
+    pub fn create_pod_storage<T, Mut: Mutability>(&self, data: &[T]) -> PodStorageBuffer<T, Mut>
+    where
+        T: Pod + ShaderSize,
+    {
+        const { check_layout::<T>() };
+
+        let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::STORAGE,
+            contents: bytemuck::cast_slice(data),
+        });
+
+        self.pod_storage(buffer, data.len() as u64)
+    }
+
+    /// Creates a zeroed storage buffer that can hold `len` elements.
+    pub fn create_pod_storage_empty<T, Mut: Mutability>(&self, len: u64) -> PodStorageBuffer<T, Mut>
+    where
+        T: Pod + ShaderSize,
+    {
+        const { check_layout::<T>() };
+
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: None,
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::STORAGE,
+            size: len * mem::size_of::<T>() as u64,
+            mapped_at_creation: false,
+        });
+
+        self.pod_storage(buffer, len)
+    }
+
+    fn pod_storage<T, Mut: Mutability>(
+        &self,
+        buffer: Buffer,
+        len: u64,
+    ) -> PodStorageBuffer<T, Mut> {
+        let id = BufferId::new();
+        self.binding_manager.add_resource(id, buffer);
+        PodStorageBuffer {
+            gpu: self.clone(),
+            buffer: id,
+            len: AtomicU64::new(len),
+
+            _type: PhantomData,
+            _mut: PhantomData,
+        }
+    }
+}
+
+const fn check_layout<T: ShaderSize>() {
+    assert!(
+        T::METADATA.is_pod(),
+        "Type must be a scalar, vector, matrix, or array of them without padding"
+    );
+    assert!(
+        mem::size_of::<T>() as u64 == array_stride::<T>(),
+        "Type's size doesn't match its WGSL array stride"
+    );
+}
+
+impl<T, Mut: Mutability> BufferBinding for PodStorageBuffer<T, Mut> {
+    fn get_id(&self) -> BufferId {
+        self.buffer
+    }
+}
+
+impl<T> Bindable for PodStorageBuffer<T, Mutable> {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Buffer(self.buffer)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl<T> Bindable for PodStorageBuffer<T, Immutable> {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Buffer(self.buffer)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl<T, Mut: Mutability> Drop for PodStorageBuffer<T, Mut> {
+    fn drop(&mut self) {
+        self.gpu.binding_manager.remove_resource(self.buffer);
+    }
+}
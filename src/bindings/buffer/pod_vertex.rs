@@ -0,0 +1,176 @@
+use std::{
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bytemuck::Pod;
+use parking_lot::MappedRwLockReadGuard;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindingType, Buffer, BufferDescriptor, BufferUsages, VERTEX_STRIDE_ALIGNMENT,
+};
+
+use crate::{
+    bindings::{Bindable, BindableResourceId},
+    gpu::Gpu,
+    misc::ids::BufferId,
+};
+
+use super::{download, BufferBinding, VertexBinding};
+
+/// Vertices or instances of plain old data, copied to and from the GPU as is.
+/// The vertex layout passed to the render pipeline should describe `T`'s
+/// `#[repr(C)]` layout, with an array stride of `size_of::<T>()`. The buffer
+/// can also be bound as a read-write storage buffer, for example to update the
+/// vertices from a compute shader.
+pub struct PodVertexBuffer<T> {
+    gpu: Gpu,
+    buffer: BufferId,
+    /// The number of vertices holding data, set by uploads.
+    len: AtomicU64,
+    _type: PhantomData<T>,
+}
+
+impl<T: Pod> PodVertexBuffer<T> {
+    pub(crate) fn get(&self) -> MappedRwLockReadGuard<Buffer> {
+        MappedRwLockReadGuard::map(self.gpu.binding_manager.get_resource(self.buffer), |x| {
+            x.expect_buffer()
+        })
+    }
+
+    /// The number of vertices the buffer was created with or last uploaded.
+    /// As uploads only grow the allocation, it may hold more vertices than
+    /// this.
+    pub fn len(&self) -> u64 {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the buffer holds no vertices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Uploads data into the buffer, reallocating it if it is too small.
+    pub fn upload(&self, data: &[T]) {
+        let bytes = bytemuck::cast_slice(data);
+        self.len.store(data.len() as u64, Ordering::Relaxed);
+
+        let this = self.get();
+        if bytes.len() as u64 > this.size() {
+            drop(this);
+            let replacement = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: bytes,
+                usage: BufferUsages::COPY_DST
+                    | BufferUsages::COPY_SRC
+                    | BufferUsages::VERTEX
+                    | BufferUsages::STORAGE,
+            });
+
+            let binding_manager = &self.gpu.binding_manager;
+            binding_manager.add_resource(self.buffer, replacement);
+            binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
+        } else {
+            self.gpu.write_buffer(&this, 0, bytes);
+        }
+    }
+
+    /// Downloads every vertex in the buffer from the GPU in a blocking manner.
+    pub fn download(&self) -> Vec<T> {
+        self.download_range(0..self.len())
+    }
+
+    /// Downloads the vertices in `range` from the GPU in a blocking manner.
+    pub fn download_range(&self, range: Range<u64>) -> Vec<T> {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "Vertices {range:?} are out of bounds"
+        );
+        download::download_pod(&self.gpu, &self.get(), range)
+    }
+}
+
+impl Gpu {
+    /// Creates a vertex or instance buffer that uploads `data` without any
+    /// conversion.
+    pub fn create_pod_vertex<T: Pod>(&self, data: &[T]) -> PodVertexBuffer<T> {
+        const { check_stride::<T>() };
+
+        let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            usage: BufferUsages::COPY_DST
+                | BufferUsages::COPY_SRC
+                | BufferUsages::VERTEX
+                | BufferUsages::STORAGE,
+            contents: bytemuck::cast_slice(data),
+        });
+
+        self.pod_vertex(buffer, data.len() as u64)
+    }
+
+    /// Creates a zeroed vertex or instance buffer that can hold `len` elements.
+    pub fn create_pod_vertex_empty<T: Pod>(&self, len: u64) -> PodVertexBuffer<T> {
+        const { check_stride::<T>() };
+
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: len * mem::size_of::<T>() as u64,
+            usage: BufferUsages::COPY_DST
+                | BufferUsages::COPY_SRC
+                | BufferUsages::VERTEX
+                | BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        self.pod_vertex(buffer, len)
+    }
+
+    fn pod_vertex<T>(&self, buffer: Buffer, len: u64) -> PodVertexBuffer<T> {
+        let id = BufferId::new();
+        self.binding_manager.add_resource(id, buffer);
+        PodVertexBuffer {
+            gpu: self.clone(),
+            buffer: id,
+            len: AtomicU64::new(len),
+            _type: PhantomData,
+        }
+    }
+}
+
+const fn check_stride<T>() {
+    assert!(mem::size_of::<T>() > 0, "Vertex type can't be zero-sized");
+    assert!(
+        (mem::size_of::<T>() as u64).is_multiple_of(VERTEX_STRIDE_ALIGNMENT),
+        "Vertex size must be a multiple of four bytes"
+    );
+}
+
+impl<T> BufferBinding for PodVertexBuffer<T> {
+    fn get_id(&self) -> BufferId {
+        self.buffer
+    }
+}
+
+impl<T> VertexBinding for PodVertexBuffer<T> {}
+
+impl<T> Bindable for PodVertexBuffer<T> {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Buffer(self.buffer)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl<T> Drop for PodVertexBuffer<T> {
+    fn drop(&mut self) {
+        self.gpu.binding_manager.remove_resource(self.buffer);
+    }
+}
@@ -0,0 +1,213 @@
+use std::ops::Range;
+
+use crossbeam_channel::{Receiver, Sender};
+use encase::{internal::CreateFrom, DynamicStorageBuffer, ShaderSize, ShaderType};
+use wgpu::{Buffer, MapMode};
+
+use crate::gpu::Gpu;
+
+use super::{download, BufferBinding};
+
+/// Streams copies of a buffer back to the CPU without ever waiting on the
+/// GPU, for things like plotting simulation statistics every frame.
+///
+/// Each call to [`ReadbackRing::read`] queues a copy of the buffer, which is
+/// submitted with the next dispatch, render pass, or call to
+/// [`Gpu::flush_dispatch_queue`], and returns the most recent copy that has
+/// finished. Results will usually be a few frames old. Up to `depth` copies
+/// can be in flight at once; if all of them are still pending, no new copy is
+/// made that frame.
+///
+/// ```rust,ignore
+/// let mut energy = gpu.create_readback_ring::<f32>(3);
+/// loop {
+///     simulate.queue_dispatch(workgroups);
+///     if let Some(energy) = energy.read(&total_energy) {
+///         println!("{energy}");
+///     }
+///     gpu.flush_dispatch_queue();
+/// }
+/// ```
+pub struct ReadbackRing<T> {
+    gpu: Gpu,
+
+    depth: usize,
+    in_flight: usize,
+    idle: Vec<Buffer>,
+
+    frame: u64,
+    latest: Option<(u64, T)>,
+
+    tx: Sender<Completed<T>>,
+    rx: Receiver<Completed<T>>,
+}
+
+/// A staging buffer that has finished mapping.
+struct Completed<T> {
+    frame: u64,
+    mapped: bool,
+    staging: Buffer,
+
+    range: Range<u64>,
+    aligned: Range<u64>,
+    decode: fn(&[u8]) -> T,
+}
+
+impl<T: ShaderType + CreateFrom + 'static> ReadbackRing<T> {
+    /// Queues a copy of the whole buffer and returns the latest finished copy,
+    /// if any have finished yet.
+    pub fn read(&mut self, buffer: &impl BufferBinding) -> Option<&T> {
+        self.read_with(
+            buffer,
+            |size| 0..size,
+            |data| {
+                let mut store = DynamicStorageBuffer::new(data);
+                store.create().unwrap()
+            },
+        )
+    }
+}
+
+impl<Elem: ShaderSize + CreateFrom + 'static> ReadbackRing<Vec<Elem>> {
+    /// Queues a copy of the elements in `range` of the buffer and returns the
+    /// latest finished copy, if any have finished yet.
+    pub fn read_range(
+        &mut self,
+        buffer: &impl BufferBinding,
+        range: Range<u64>,
+    ) -> Option<&Vec<Elem>> {
+        self.read_with(
+            buffer,
+            |size| download::array_range::<Elem>(range, size),
+            |data| download::create_array(data.to_vec()),
+        )
+    }
+}
+
+impl<T: 'static> ReadbackRing<T> {
+    /// The most recent finished copy, without queuing a new one.
+    pub fn latest(&mut self) -> Option<&T> {
+        self.receive();
+        self.latest.as_ref().map(|(_, data)| data)
+    }
+
+    /// The number of copies that haven't finished yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    fn read_with(
+        &mut self,
+        buffer: &impl BufferBinding,
+        range: impl FnOnce(u64) -> Range<u64>,
+        decode: fn(&[u8]) -> T,
+    ) -> Option<&T> {
+        self.receive();
+        if self.in_flight < self.depth {
+            self.enqueue(buffer, range, decode);
+        }
+
+        self.latest.as_ref().map(|(_, data)| data)
+    }
+
+    /// Collects any copies that have finished mapping, keeping the newest.
+    fn receive(&mut self) {
+        self.gpu.poll();
+
+        while let Ok(completed) = self.rx.try_recv() {
+            self.in_flight -= 1;
+            if !completed.mapped {
+                continue;
+            }
+
+            if self
+                .latest
+                .as_ref()
+                .is_none_or(|(frame, _)| *frame < completed.frame)
+            {
+                let data = completed.staging.slice(..).get_mapped_range();
+                let data =
+                    (completed.decode)(download::trim(&data, &completed.range, &completed.aligned));
+                self.latest = Some((completed.frame, data));
+            }
+
+            completed.staging.unmap();
+            self.idle.push(completed.staging);
+        }
+    }
+
+    fn enqueue(
+        &mut self,
+        buffer: &impl BufferBinding,
+        range: impl FnOnce(u64) -> Range<u64>,
+        decode: fn(&[u8]) -> T,
+    ) {
+        let source = self.gpu.binding_manager.get_resource(buffer.get_id());
+        let source = source.expect_buffer();
+
+        let range = range(source.size());
+        let aligned = download::align(&range, source.size());
+        let size = download::staging_size(&aligned);
+
+        let staging = match self.idle.pop() {
+            Some(staging) if staging.size() >= size => staging,
+            _ => self.gpu.staging.take(&self.gpu.device, size),
+        };
+
+        let frame = self.frame;
+        self.frame += 1;
+        self.in_flight += 1;
+
+        // Buffers can only be mapped once the copy has been submitted, so the
+        // mapping is requested when the work finishes.
+        let tx = self.tx.clone();
+        let mapping = staging.clone();
+        let copy = aligned.clone();
+        self.gpu.queue_dispatch_callback(
+            |encoder| {
+                encoder.copy_buffer_to_buffer(
+                    source,
+                    copy.start,
+                    &staging,
+                    0,
+                    copy.end - copy.start,
+                );
+            },
+            move || {
+                let staging = mapping.clone();
+                mapping.slice(..).map_async(MapMode::Read, move |result| {
+                    let _ = tx.send(Completed {
+                        frame,
+                        mapped: result.is_ok(),
+                        staging,
+                        range,
+                        aligned,
+                        decode,
+                    });
+                });
+            },
+        );
+    }
+}
+
+impl Gpu {
+    /// Creates a readback ring that allows up to `depth` copies in flight.
+    pub fn create_readback_ring<T>(&self, depth: usize) -> ReadbackRing<T> {
+        assert!(depth > 0, "Readback ring depth must be at least one");
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        ReadbackRing {
+            gpu: self.clone(),
+
+            depth,
+            in_flight: 0,
+            idle: Vec::with_capacity(depth),
+
+            frame: 0,
+            latest: None,
+
+            tx,
+            rx,
+        }
+    }
+}
@@ -1,42 +1,58 @@
-use std::{marker::PhantomData, thread};
+use std::{
+    marker::PhantomData,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use encase::{
     internal::{CreateFrom, WriteInto},
-    DynamicStorageBuffer, ShaderType,
+    DynamicStorageBuffer, ShaderSize, ShaderType,
 };
 use parking_lot::MappedRwLockReadGuard;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    BindingType, Buffer, BufferDescriptor, BufferUsages, MaintainBase, MapMode,
+    BindingType, Buffer, BufferDescriptor, BufferUsages,
 };
 
 use crate::{
+    algorithms,
     bindings::{
         buffer::mutability::{Immutable, Mutability, Mutable},
         Bindable, BindableResourceId,
     },
     gpu::Gpu,
-    misc::{ids::BufferId, thread_ptr::ThreadSafePtr},
+    misc::ids::BufferId,
 };
 
-use super::BufferBinding;
+use super::{array_stride, download, BufferBinding, RawStorage};
+
+/// Number of invocations in each workgroup of `fill.wgsl`.
+const FILL_WORKGROUP_SIZE: u32 = 256;
 
 /// A storage buffer is a buffer that can be read from or written to in the shader
 pub struct StorageBuffer<T, Mut: Mutability> {
     pub(crate) gpu: Gpu,
     pub(crate) buffer: BufferId,
+    /// The number of bytes holding data, set by uploads and grown by partial
+    /// writes. This may be less than the size of the allocation.
+    size: AtomicU64,
 
     _type: PhantomData<T>,
     _mut: PhantomData<Mut>,
 }
 
 impl<T: ShaderType + WriteInto + CreateFrom, Mut: Mutability> StorageBuffer<T, Mut> {
-    fn get(&self) -> MappedRwLockReadGuard<Buffer> {
+    pub(crate) fn get(&self) -> MappedRwLockReadGuard<Buffer> {
         MappedRwLockReadGuard::map(self.gpu.binding_manager.get_resource(self.buffer), |x| {
             x.expect_buffer()
         })
     }
 
+    /// The number of bytes holding data, which `len` is counted from.
+    pub(crate) fn byte_len(&self) -> u64 {
+        self.size.load(Ordering::Relaxed)
+    }
+
     /// Uploads data into the buffer
     pub fn upload(&self, data: &T) {
         self.upload_inner(data, false)
@@ -51,7 +67,13 @@ impl<T: ShaderType + WriteInto + CreateFrom, Mut: Mutability> StorageBuffer<T, M
         let mut bytes = Vec::new();
         let mut storage = encase::StorageBuffer::new(&mut bytes);
         storage.write(data).unwrap();
+        self.upload_bytes(&bytes, shrink);
+    }
 
+    /// Writes already encoded data into the buffer, reallocating it if it's
+    /// too small, or if it's a different size and `shrink` is set.
+    pub(crate) fn upload_bytes(&self, bytes: &[u8], shrink: bool) {
+        self.size.store(bytes.len() as u64, Ordering::Relaxed);
         let buffer = self.get();
         let current_size = buffer.size() as usize;
 
@@ -59,7 +81,7 @@ impl<T: ShaderType + WriteInto + CreateFrom, Mut: Mutability> StorageBuffer<T, M
             drop(buffer);
             let replacement = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
                 label: None,
-                contents: &bytes,
+                contents: bytes,
                 usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::STORAGE,
             });
 
@@ -67,67 +89,211 @@ impl<T: ShaderType + WriteInto + CreateFrom, Mut: Mutability> StorageBuffer<T, M
             binding_manager.add_resource(self.buffer, replacement);
             binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
         } else {
-            self.gpu.queue.write_buffer(&buffer, 0, &bytes);
+            self.gpu.write_buffer(&buffer, 0, bytes);
         }
     }
 
     /// Downloads the buffer from the GPU in a blocking manner. This can be
-    /// pretty slow.
+    /// pretty slow. The whole allocation is downloaded, so after uploading a
+    /// smaller array with [`StorageBuffer::upload`], the elements past
+    /// [`StorageBuffer::len`] are left over from before.
     pub fn download(&self) -> T {
         let buffer = self.get();
-        let staging = self.gpu.device.create_buffer(&BufferDescriptor {
-            label: None,
-            size: buffer.size(),
-            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
-            mapped_at_creation: false,
-        });
+        let data = download::download(&self.gpu, &buffer, 0..buffer.size());
 
-        self.gpu.immediate_dispatch(|encoder| {
-            encoder.copy_buffer_to_buffer(&buffer, 0, &staging, 0, buffer.size());
+        let mut store = DynamicStorageBuffer::new(data);
+        store.create().unwrap()
+    }
+
+    /// Requests the download of the buffer. The provided callback will be
+    /// executed once the transfer finishes.
+    pub fn download_async(&self, func: impl FnOnce(T) + Send + 'static) {
+        let buffer = self.get();
+        download::download_async(&self.gpu, &buffer, 0..buffer.size(), move |data| {
+            let mut store = DynamicStorageBuffer::new(data);
+            func(store.create().unwrap());
         });
+    }
+}
 
-        let slice = staging.slice(..);
+impl<T: ShaderType + WriteInto + CreateFrom, Mut: Mutability> StorageBuffer<T, Mut> {
+    /// Sets every byte of the buffer to zero.
+    pub fn clear(&self) {
+        self.clear_inner(true);
+    }
 
-        let (tx, rx) = crossbeam_channel::bounded(1);
-        slice.map_async(MapMode::Read, move |_| tx.send(()).unwrap());
+    /// Queues setting every byte of the buffer to zero with the next compute
+    /// dispach, render pass, or call to [`Gpu::flush_dispatch_queue`].
+    pub fn queue_clear(&self) {
+        self.clear_inner(false);
+    }
 
-        self.gpu.device.poll(MaintainBase::Wait);
-        rx.recv().unwrap();
+    /// Copies the bytes in `range` of `source` into this buffer, starting at
+    /// `offset` bytes in. The range and offset must be multiples of four. See
+    /// [`Gpu::copy_buffer`] for copying into other kinds of buffers.
+    pub fn copy_from(&self, source: &impl BufferBinding, range: Range<u64>, offset: u64) {
+        let end = offset + range.end.saturating_sub(range.start);
+        self.gpu.copy_buffer(source, range, self, offset);
+        self.extend_size(end);
+    }
 
-        let data = slice.get_mapped_range().to_vec();
-        let mut store = encase::DynamicStorageBuffer::new(data);
+    /// Queues a [`StorageBuffer::copy_from`] with the next compute dispach,
+    /// render pass, or call to [`Gpu::flush_dispatch_queue`].
+    pub fn queue_copy_from(&self, source: &impl BufferBinding, range: Range<u64>, offset: u64) {
+        let end = offset + range.end.saturating_sub(range.start);
+        self.gpu.queue_copy_buffer(source, range, self, offset);
+        self.extend_size(end);
+    }
 
-        store.create().unwrap()
+    fn clear_inner(&self, immediate: bool) {
+        let buffer = self.get();
+        self.gpu
+            .dispach(|encoder| encoder.clear_buffer(&buffer, 0, None), immediate);
+        self.extend_size(buffer.size());
     }
 
-    /// Requests the download of the buffer. The provided callback will be
-    /// executed once the transfer finishes.
-    pub fn download_async(&self, func: impl FnOnce(T) + Send + 'static) {
+    /// Grows the number of bytes counted by `len` to at least `end`.
+    fn extend_size(&self, end: u64) {
+        self.size.fetch_max(end, Ordering::Relaxed);
+    }
+}
+
+impl<Elem, Mut: Mutability> StorageBuffer<Vec<Elem>, Mut>
+where
+    Elem: ShaderType + ShaderSize + WriteInto + CreateFrom,
+{
+    /// The number of elements the buffer was created with or last uploaded.
+    /// As uploads only grow the allocation, it may hold more elements than
+    /// this, which shaders will still see in `arrayLength`.
+    ///
+    /// Partial writes with [`upload_range`](Self::upload_range),
+    /// [`write_indices`](Self::write_indices) and
+    /// [`copy_from`](StorageBuffer::copy_from) extend the length if they write
+    /// past it, and [`fill`](Self::fill) and [`clear`](StorageBuffer::clear)
+    /// extend it to the whole allocation.
+    pub fn len(&self) -> u64 {
+        self.size.load(Ordering::Relaxed) / array_stride::<Elem>()
+    }
+
+    /// Returns `true` if the buffer holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Downloads the elements in `range` from the GPU in a blocking manner.
+    /// Only the bytes holding those elements are copied.
+    pub fn download_range(&self, range: Range<u64>) -> Vec<Elem> {
         let buffer = self.get();
-        let staging = self.gpu.device.create_buffer(&BufferDescriptor {
-            label: None,
-            size: buffer.size(),
-            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
-            mapped_at_creation: false,
+        let range = download::array_range::<Elem>(range, buffer.size());
+        download::create_array(download::download(&self.gpu, &buffer, range))
+    }
+
+    /// Requests the download of the elements in `range`. The provided callback
+    /// will be executed once the transfer finishes.
+    pub fn download_range_async(
+        &self,
+        range: Range<u64>,
+        func: impl FnOnce(Vec<Elem>) + Send + 'static,
+    ) {
+        let buffer = self.get();
+        let range = download::array_range::<Elem>(range, buffer.size());
+        download::download_async(&self.gpu, &buffer, range, move |data| {
+            func(download::create_array(data))
         });
-        let staging = Box::leak(Box::new(staging));
+    }
 
-        self.gpu.immediate_dispatch(|encoder| {
-            encoder.copy_buffer_to_buffer(&buffer, 0, staging, 0, buffer.size());
+    /// Downloads the element at `index` from the GPU in a blocking manner.
+    pub fn download_element(&self, index: u64) -> Elem {
+        let buffer = self.get();
+        let range = download::element_range::<Elem>(index, buffer.size());
+        let mut store = DynamicStorageBuffer::new(download::download(&self.gpu, &buffer, range));
+        store.create().unwrap()
+    }
+
+    /// Requests the download of the element at `index`. The provided callback
+    /// will be executed once the transfer finishes.
+    pub fn download_element_async(&self, index: u64, func: impl FnOnce(Elem) + Send + 'static) {
+        let buffer = self.get();
+        let range = download::element_range::<Elem>(index, buffer.size());
+        download::download_async(&self.gpu, &buffer, range, move |data| {
+            let mut store = DynamicStorageBuffer::new(data);
+            func(store.create().unwrap());
         });
+    }
 
-        let staging = ThreadSafePtr(staging as *mut Buffer);
-        let slice = staging.deref().slice(..);
+    /// Uploads `data` into the buffer, starting at the element with index
+    /// `offset`. The rest of the buffer is left untouched.
+    pub fn upload_range(&self, offset: u64, data: &[Elem]) {
+        let mut bytes = Vec::new();
+        let mut storage = encase::StorageBuffer::new(&mut bytes);
+        storage.write(&data).unwrap();
 
-        slice.map_async(MapMode::Read, move |_| {
-            let data = slice.get_mapped_range().to_vec();
-            unsafe { drop(Box::from_raw(staging.deref_mut())) };
+        self.write_at(offset, &bytes);
+    }
 
-            thread::spawn(move || {
-                let mut store = DynamicStorageBuffer::new(data);
-                func(store.create().unwrap());
-            });
-        });
+    /// Uploads each `(index, value)` pair into the element at that index.
+    pub fn write_indices(&self, data: &[(u32, Elem)]) {
+        let mut bytes = Vec::new();
+        for (index, value) in data {
+            bytes.clear();
+            let mut storage = encase::StorageBuffer::new(&mut bytes);
+            storage.write(value).unwrap();
+
+            self.write_at(*index as u64, &bytes);
+        }
+    }
+
+    fn write_at(&self, index: u64, bytes: &[u8]) {
+        let buffer = self.get();
+        let offset = index * array_stride::<Elem>();
+        assert!(
+            offset + bytes.len() as u64 <= buffer.size(),
+            "Write to element {index} is out of bounds"
+        );
+
+        self.gpu.write_buffer(&buffer, offset, bytes);
+        self.extend_size(offset + bytes.len() as u64);
+    }
+
+    /// Sets every element of the buffer to `value`.
+    pub fn fill(&self, value: &Elem) {
+        self.fill_inner(value, true);
+    }
+
+    /// Queues setting every element of the buffer to `value` with the next
+    /// compute dispach, render pass, or call to [`Gpu::flush_dispatch_queue`].
+    pub fn queue_fill(&self, value: &Elem) {
+        self.fill_inner(value, false);
+    }
+
+    fn fill_inner(&self, value: &Elem, immediate: bool) {
+        let mut bytes = Vec::new();
+        let mut storage = encase::StorageBuffer::new(&mut bytes);
+        storage.write(value).unwrap();
+        bytes.resize(array_stride::<Elem>() as usize, 0);
+
+        let pattern = self
+            .gpu
+            .create_storage::<Vec<u32>, Mutable>(&bytemuck::pod_collect_to_vec(&bytes));
+        let size = self.get().size();
+        let words = (size / 4) as u32;
+        let uniform = self.gpu.create_uniform(&words);
+
+        let mut pipeline = self
+            .gpu
+            .kernel(include_str!("fill.wgsl"), &[])
+            .bind(&uniform)
+            .bind(&RawStorage(self.buffer))
+            .bind(&pattern)
+            .finish();
+
+        let workgroups = algorithms::workgroups(words.div_ceil(FILL_WORKGROUP_SIZE));
+        if immediate {
+            pipeline.dispatch(workgroups);
+        } else {
+            pipeline.queue_dispatch(workgroups);
+        }
+        self.extend_size(size);
     }
 }
 
@@ -141,6 +307,7 @@ impl Gpu {
         storage.write(data).unwrap();
 
         let id = BufferId::new();
+        let size = AtomicU64::new(buffer.len() as u64);
         let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
             usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::STORAGE,
@@ -151,6 +318,7 @@ impl Gpu {
         StorageBuffer {
             gpu: self.clone(),
             buffer: id,
+            size,
 
             _type: PhantomData,
             _mut: PhantomData,
@@ -173,6 +341,7 @@ impl Gpu {
         StorageBuffer {
             gpu: self.clone(),
             buffer: id,
+            size: AtomicU64::new(size),
 
             _type: PhantomData,
             _mut: PhantomData,
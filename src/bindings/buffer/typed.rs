@@ -0,0 +1,295 @@
+use std::marker::PhantomData;
+
+use encase::{
+    internal::{CreateFrom, WriteInto},
+    DynamicStorageBuffer, ShaderType,
+};
+use parking_lot::MappedRwLockReadGuard;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
+};
+
+use crate::{
+    bindings::{Bindable, BindableResourceId},
+    gpu::Gpu,
+    misc::ids::BufferId,
+};
+
+use super::{download, BufferBinding, VertexBinding};
+
+/// Builds a [`TypedBuffer`] that can be used in several roles at once, for
+/// example as the output of a compute shader and as a vertex buffer:
+///
+/// ```rust,ignore
+/// let particles = gpu
+///     .buffer::<Vec<Particle>>()
+///     .storage()
+///     .vertex()
+///     .init(&particles);
+/// ```
+pub struct BufferBuilder<T> {
+    gpu: Gpu,
+    usage: BufferUsages,
+    read_only: bool,
+    _type: PhantomData<T>,
+}
+
+/// A buffer created through [`Gpu::buffer`]. It can be bound as a storage or
+/// uniform buffer, drawn as a vertex buffer, or used for indirect dispatches,
+/// as long as the role was enabled when it was built.
+///
+/// Bound directly, the buffer is a storage buffer if it was built with
+/// [`storage`](BufferBuilder::storage) and a uniform buffer otherwise. Bind
+/// [`as_storage`](Self::as_storage) or [`as_uniform`](Self::as_uniform) to pick
+/// the role explicitly.
+pub struct TypedBuffer<T> {
+    gpu: Gpu,
+    buffer: BufferId,
+    usage: BufferUsages,
+    read_only: bool,
+    _type: PhantomData<T>,
+}
+
+impl<T> BufferBuilder<T> {
+    /// Allows binding the buffer as a read-write storage buffer.
+    pub fn storage(mut self) -> Self {
+        self.usage |= BufferUsages::STORAGE;
+        self
+    }
+
+    /// Allows binding the buffer as a read-only storage buffer.
+    pub fn storage_read_only(mut self) -> Self {
+        self.read_only = true;
+        self.storage()
+    }
+
+    /// Allows binding the buffer as a uniform buffer. If the buffer is also a
+    /// storage buffer, bind it with [`TypedBuffer::as_uniform`] to use it as a
+    /// uniform.
+    pub fn uniform(mut self) -> Self {
+        self.usage |= BufferUsages::UNIFORM;
+        self
+    }
+
+    /// Allows drawing the buffer as vertices or instances.
+    pub fn vertex(mut self) -> Self {
+        self.usage |= BufferUsages::VERTEX;
+        self
+    }
+
+    /// Allows using the buffer as the arguments to an indirect dispatch.
+    pub fn indirect(mut self) -> Self {
+        self.usage |= BufferUsages::INDIRECT;
+        self
+    }
+
+    /// Adds any other usages to the buffer.
+    pub fn usage(mut self, usage: BufferUsages) -> Self {
+        self.usage |= usage;
+        self
+    }
+
+    /// Creates the buffer with the given initial state. Panics if neither
+    /// [`storage`](Self::storage) nor [`uniform`](Self::uniform) was set, as
+    /// the buffer couldn't be bound to a pipeline.
+    pub fn init(self, data: &T) -> TypedBuffer<T>
+    where
+        T: ShaderType + WriteInto,
+    {
+        self.check_roles();
+        let mut buffer = Vec::new();
+        let mut storage = encase::StorageBuffer::new(&mut buffer);
+        storage.write(data).unwrap();
+
+        let buffer = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            usage: self.usage,
+            contents: &buffer,
+        });
+
+        self.finish(buffer)
+    }
+
+    /// Creates a zeroed buffer of `size` bytes. Panics under the same
+    /// conditions as [`init`](Self::init).
+    pub fn empty(self, size: u64) -> TypedBuffer<T> {
+        self.check_roles();
+        let buffer = self.gpu.device.create_buffer(&BufferDescriptor {
+            label: None,
+            usage: self.usage,
+            size,
+            mapped_at_creation: false,
+        });
+
+        self.finish(buffer)
+    }
+
+    fn check_roles(&self) {
+        assert!(
+            self.usage
+                .intersects(BufferUsages::STORAGE | BufferUsages::UNIFORM),
+            "Buffer must be built with `storage` or `uniform` to be bound to a pipeline"
+        );
+    }
+
+    fn finish(self, buffer: Buffer) -> TypedBuffer<T> {
+        let id = BufferId::new();
+        self.gpu.binding_manager.add_resource(id, buffer);
+
+        TypedBuffer {
+            gpu: self.gpu,
+            buffer: id,
+            usage: self.usage,
+            read_only: self.read_only,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> TypedBuffer<T> {
+    fn get(&self) -> MappedRwLockReadGuard<Buffer> {
+        MappedRwLockReadGuard::map(self.gpu.binding_manager.get_resource(self.buffer), |x| {
+            x.expect_buffer()
+        })
+    }
+
+    /// The roles the buffer was created with.
+    pub fn usage(&self) -> BufferUsages {
+        self.usage
+    }
+
+    /// Binds the buffer as a storage buffer. Panics if it wasn't built with
+    /// [`storage`](BufferBuilder::storage).
+    pub fn as_storage(&self) -> BufferRole<'_, T> {
+        assert!(
+            self.usage.contains(BufferUsages::STORAGE),
+            "Buffer must be built with `storage` to be bound as a storage buffer"
+        );
+        BufferRole {
+            buffer: self,
+            ty: BufferBindingType::Storage {
+                read_only: self.read_only,
+            },
+        }
+    }
+
+    /// Binds the buffer as a uniform buffer. Panics if it wasn't built with
+    /// [`uniform`](BufferBuilder::uniform).
+    pub fn as_uniform(&self) -> BufferRole<'_, T> {
+        assert!(
+            self.usage.contains(BufferUsages::UNIFORM),
+            "Buffer must be built with `uniform` to be bound as a uniform buffer"
+        );
+        BufferRole {
+            buffer: self,
+            ty: BufferBindingType::Uniform,
+        }
+    }
+}
+
+/// A [`TypedBuffer`] bound in a single role, from [`TypedBuffer::as_storage`]
+/// or [`TypedBuffer::as_uniform`].
+pub struct BufferRole<'a, T> {
+    buffer: &'a TypedBuffer<T>,
+    ty: BufferBindingType,
+}
+
+impl<T: ShaderType + WriteInto + CreateFrom> TypedBuffer<T> {
+    /// Uploads data into the buffer, reallocating it if it is too small.
+    pub fn upload(&self, data: &T) {
+        let mut bytes = Vec::new();
+        let mut storage = encase::StorageBuffer::new(&mut bytes);
+        storage.write(data).unwrap();
+
+        let buffer = self.get();
+        if bytes.len() as u64 > buffer.size() {
+            drop(buffer);
+            let replacement = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: &bytes,
+                usage: self.usage,
+            });
+
+            let binding_manager = &self.gpu.binding_manager;
+            binding_manager.add_resource(self.buffer, replacement);
+            binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
+        } else {
+            self.gpu.write_buffer(&buffer, 0, &bytes);
+        }
+    }
+
+    /// Downloads the buffer from the GPU in a blocking manner.
+    pub fn download(&self) -> T {
+        let buffer = self.get();
+        let data = download::download(&self.gpu, &buffer, 0..buffer.size());
+
+        let mut store = DynamicStorageBuffer::new(data);
+        store.create().unwrap()
+    }
+
+    /// Requests the download of the buffer. The provided callback will be
+    /// executed once the transfer finishes.
+    pub fn download_async(&self, func: impl FnOnce(T) + Send + 'static) {
+        let buffer = self.get();
+        download::download_async(&self.gpu, &buffer, 0..buffer.size(), move |data| {
+            let mut store = DynamicStorageBuffer::new(data);
+            func(store.create().unwrap());
+        });
+    }
+}
+
+impl Gpu {
+    /// Starts building a buffer with a custom set of roles. Buffers can always
+    /// be copied to and from.
+    pub fn buffer<T>(&self) -> BufferBuilder<T> {
+        BufferBuilder {
+            gpu: self.clone(),
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            read_only: false,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> BufferBinding for TypedBuffer<T> {
+    fn get_id(&self) -> BufferId {
+        self.buffer
+    }
+}
+
+impl<T> VertexBinding for TypedBuffer<T> {}
+
+impl<T> Bindable for TypedBuffer<T> {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Buffer(self.buffer)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        // Building checks that the buffer is a storage or uniform buffer.
+        match self.usage.contains(BufferUsages::STORAGE) {
+            true => self.as_storage().binding_type(),
+            false => self.as_uniform().binding_type(),
+        }
+    }
+}
+
+impl<T> Bindable for BufferRole<'_, T> {
+    fn resource_id(&self) -> BindableResourceId {
+        self.buffer.resource_id()
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: self.ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl<T> Drop for TypedBuffer<T> {
+    fn drop(&mut self) {
+        self.gpu.binding_manager.remove_resource(self.buffer);
+    }
+}
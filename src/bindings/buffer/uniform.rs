@@ -2,12 +2,12 @@ use std::marker::PhantomData;
 
 use encase::{
     internal::{CreateFrom, WriteInto},
-    ShaderType, StorageBuffer,
+    DynamicStorageBuffer, ShaderType, StorageBuffer,
 };
 use parking_lot::MappedRwLockReadGuard;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    BindingType, Buffer, BufferUsages,
+    BindingType, Buffer, BufferUsages, COPY_BUFFER_ALIGNMENT,
 };
 
 use crate::{
@@ -16,29 +16,66 @@ use crate::{
     misc::ids::BufferId,
 };
 
-use super::BufferBinding;
+use super::{download, BufferBinding};
 
 /// A uniform buffer is for passing small amounts of read-only data
 pub struct UniformBuffer<T> {
-    gpu: Gpu,
+    pub(crate) gpu: Gpu,
     buffer: BufferId,
     _type: PhantomData<T>,
 }
 
 impl<T: ShaderType + WriteInto + CreateFrom> UniformBuffer<T> {
-    fn get(&self) -> MappedRwLockReadGuard<Buffer> {
+    pub(crate) fn get(&self) -> MappedRwLockReadGuard<Buffer> {
         MappedRwLockReadGuard::map(self.gpu.binding_manager.get_resource(self.buffer), |x| {
             x.expect_buffer()
         })
     }
 
-    /// Uploads data into the buffer
+    /// Uploads data into the buffer, reallocating it if the encoded size
+    /// changed.
     pub fn upload(&self, data: &T) {
-        let mut buffer = Vec::new();
-        let mut storage = StorageBuffer::new(&mut buffer);
-        storage.write(data).unwrap();
+        let bytes = self.gpu.encode_uniform(data);
+        self.upload_bytes(&bytes);
+    }
+
+    /// Writes already encoded data into the buffer, reallocating it if the
+    /// size changed.
+    pub(crate) fn upload_bytes(&self, bytes: &[u8]) {
+        let buffer = self.get();
+        if bytes.len() as u64 != buffer.size() {
+            drop(buffer);
+            let replacement = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: bytes,
+                usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::UNIFORM,
+            });
+
+            let binding_manager = &self.gpu.binding_manager;
+            binding_manager.add_resource(self.buffer, replacement);
+            binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
+        } else {
+            self.gpu.write_buffer(&buffer, 0, bytes);
+        }
+    }
+
+    /// Downloads the buffer from the GPU in a blocking manner.
+    pub fn download(&self) -> T {
+        let buffer = self.get();
+        let data = download::download(&self.gpu, &buffer, 0..buffer.size());
 
-        self.gpu.queue.write_buffer(&self.get(), 0, &buffer);
+        let mut store = DynamicStorageBuffer::new(data);
+        store.create().unwrap()
+    }
+
+    /// Requests the download of the buffer. The provided callback will be
+    /// executed once the transfer finishes.
+    pub fn download_async(&self, func: impl FnOnce(T) + Send + 'static) {
+        let buffer = self.get();
+        download::download_async(&self.gpu, &buffer, 0..buffer.size(), move |data| {
+            let mut store = DynamicStorageBuffer::new(data);
+            func(store.create().unwrap());
+        });
     }
 }
 
@@ -48,15 +85,11 @@ impl Gpu {
     where
         T: ShaderType + WriteInto + CreateFrom,
     {
-        let mut buffer = Vec::new();
-        let mut storage = StorageBuffer::new(&mut buffer);
-        storage.write(data).unwrap();
-
         let id = BufferId::new();
         let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
-            contents: &buffer,
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::UNIFORM,
+            contents: &self.encode_uniform(data),
         });
 
         self.binding_manager.add_resource(id, buffer);
@@ -68,6 +101,30 @@ impl Gpu {
     }
 }
 
+impl Gpu {
+    /// Encodes `data` and checks that it fits within the device's
+    /// `max_uniform_buffer_binding_size`. The result is padded to a multiple of
+    /// four bytes, matching the size of buffers made by `create_buffer_init`.
+    fn encode_uniform(&self, data: &(impl ShaderType + WriteInto)) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut storage = StorageBuffer::new(&mut bytes);
+        storage.write(data).unwrap();
+
+        let limit = self.device.limits().max_uniform_buffer_binding_size;
+        assert!(
+            bytes.len() as u64 <= limit as u64,
+            "Uniform of {} bytes is larger than the device's max_uniform_buffer_binding_size of {limit} bytes",
+            bytes.len()
+        );
+
+        bytes.resize(
+            bytes.len().next_multiple_of(COPY_BUFFER_ALIGNMENT as usize),
+            0,
+        );
+        bytes
+    }
+}
+
 impl<T> BufferBinding for UniformBuffer<T> {
     fn get_id(&self) -> BufferId {
         self.buffer
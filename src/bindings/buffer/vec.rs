@@ -0,0 +1,259 @@
+use std::marker::PhantomData;
+
+use encase::{
+    internal::{CreateFrom, WriteInto},
+    ShaderSize, ShaderType,
+};
+use parking_lot::MappedRwLockReadGuard;
+use wgpu::{BindingType, Buffer, BufferDescriptor, BufferUsages};
+
+use crate::{
+    bindings::{
+        buffer::mutability::{Immutable, Mutability, Mutable},
+        Bindable, BindableResourceId,
+    },
+    gpu::Gpu,
+    misc::ids::BufferId,
+};
+
+use super::{array_stride, download, BufferBinding};
+
+/// A growable array on the GPU with [`Vec`]-like semantics. The buffer starts
+/// with the current length, so it should be bound to a struct like the
+/// following:
+///
+/// ```wgsl
+/// struct Particles {
+///     len: u32,
+///     data: array<Particle>,
+/// }
+/// ```
+///
+/// Like a `Vec`, the capacity doubles when it runs out of space. Any pipelines
+/// using the buffer will rebind it automatically after a reallocation.
+pub struct GpuVec<T, Mut: Mutability> {
+    gpu: Gpu,
+    buffer: BufferId,
+    len: u64,
+    capacity: u64,
+
+    _type: PhantomData<T>,
+    _mut: PhantomData<Mut>,
+}
+
+impl<T, Mut> GpuVec<T, Mut>
+where
+    T: ShaderType + ShaderSize + WriteInto + CreateFrom,
+    Mut: Mutability,
+{
+    fn get(&self) -> MappedRwLockReadGuard<Buffer> {
+        MappedRwLockReadGuard::map(self.gpu.binding_manager.get_resource(self.buffer), |x| {
+            x.expect_buffer()
+        })
+    }
+
+    /// The number of elements in the vector.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Makes sure there is space for at least `additional` more elements.
+    pub fn reserve(&mut self, additional: u64) {
+        let needed = self.len + additional;
+        if needed <= self.capacity {
+            return;
+        }
+
+        let capacity = needed.max(self.capacity * 2);
+        let buffer = create_buffer::<T>(&self.gpu, capacity);
+
+        let used = header::<T>() + self.len * array_stride::<T>();
+        self.gpu.immediate_dispatch(|encoder| {
+            encoder.copy_buffer_to_buffer(&self.get(), 0, &buffer, 0, used)
+        });
+
+        let binding_manager = &self.gpu.binding_manager;
+        binding_manager.add_resource(self.buffer, buffer);
+        binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
+        self.capacity = capacity;
+    }
+
+    /// Appends an element to the end of the vector.
+    pub fn push(&mut self, value: T) {
+        self.extend([value]);
+    }
+
+    /// Appends every element of the iterator to the end of the vector.
+    pub fn extend(&mut self, values: impl IntoIterator<Item = T>) {
+        let values = values.into_iter().collect::<Vec<_>>();
+        if values.is_empty() {
+            return;
+        }
+
+        self.reserve(values.len() as u64);
+        self.write_at(self.len, &encode(&values));
+        self.set_len(self.len + values.len() as u64);
+    }
+
+    /// Shortens the vector to `len` elements. Has no effect if the vector is
+    /// already shorter. The capacity is left unchanged.
+    pub fn truncate(&mut self, len: u64) {
+        if len < self.len {
+            self.set_len(len);
+        }
+    }
+
+    /// Removes every element from the vector.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Overwrites the element at `index`.
+    pub fn set(&mut self, index: u64, value: T) {
+        assert!(
+            index < self.len,
+            "Index {index} is out of bounds for GpuVec of length {}",
+            self.len
+        );
+
+        self.write_at(index, &encode(&value));
+    }
+
+    /// Downloads the elements of the vector in a blocking manner.
+    pub fn download(&self) -> Vec<T> {
+        let buffer = self.get();
+        let range = header::<T>()..header::<T>() + self.len * array_stride::<T>();
+        download::create_array(download::download(&self.gpu, &buffer, range))
+    }
+
+    /// Requests the download of the elements of the vector. The provided
+    /// callback will be executed once the transfer finishes.
+    pub fn download_async(&self, func: impl FnOnce(Vec<T>) + Send + 'static)
+    where
+        T: Send + 'static,
+    {
+        let buffer = self.get();
+        let range = header::<T>()..header::<T>() + self.len * array_stride::<T>();
+        download::download_async(&self.gpu, &buffer, range, move |data| {
+            func(download::create_array(data))
+        });
+    }
+
+    fn set_len(&mut self, len: u64) {
+        self.len = len;
+        let header = u32::try_from(len).expect("GpuVec length must fit in a u32");
+        self.gpu
+            .write_buffer(&self.get(), 0, bytemuck::bytes_of(&header));
+    }
+
+    fn write_at(&self, index: u64, bytes: &[u8]) {
+        let offset = header::<T>() + index * array_stride::<T>();
+        self.gpu.write_buffer(&self.get(), offset, bytes);
+    }
+}
+
+impl Gpu {
+    /// Creates a new [`GpuVec`] holding the given elements.
+    pub fn create_gpu_vec<T, Mut: Mutability>(&self, data: &[T]) -> GpuVec<T, Mut>
+    where
+        T: ShaderType + ShaderSize + WriteInto + CreateFrom + Clone,
+    {
+        let mut vec = self.create_gpu_vec_with_capacity(data.len() as u64);
+        vec.extend(data.iter().cloned());
+        vec
+    }
+
+    /// Creates an empty [`GpuVec`] with space for at least `capacity` elements.
+    pub fn create_gpu_vec_with_capacity<T, Mut: Mutability>(&self, capacity: u64) -> GpuVec<T, Mut>
+    where
+        T: ShaderType + ShaderSize + WriteInto + CreateFrom,
+    {
+        // Runtime-sized arrays need room for at least one element to be bound.
+        let capacity = capacity.max(1);
+        let id = BufferId::new();
+        self.binding_manager
+            .add_resource(id, create_buffer::<T>(self, capacity));
+
+        GpuVec {
+            gpu: self.clone(),
+            buffer: id,
+            len: 0,
+            capacity,
+
+            _type: PhantomData,
+            _mut: PhantomData,
+        }
+    }
+}
+
+/// The offset of the array after the `u32` length, which is padded to the
+/// alignment of the elements.
+const fn header<T: ShaderSize>() -> u64 {
+    T::METADATA.alignment().round_up(4)
+}
+
+fn create_buffer<T: ShaderSize>(gpu: &Gpu, capacity: u64) -> Buffer {
+    gpu.device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: header::<T>() + capacity * array_stride::<T>(),
+        usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    })
+}
+
+fn encode(value: &(impl ShaderType + WriteInto + ?Sized)) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut storage = encase::StorageBuffer::new(&mut bytes);
+    storage.write(value).unwrap();
+    bytes
+}
+
+impl<T, Mut: Mutability> BufferBinding for GpuVec<T, Mut> {
+    fn get_id(&self) -> BufferId {
+        self.buffer
+    }
+}
+
+impl<T> Bindable for GpuVec<T, Mutable> {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Buffer(self.buffer)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl<T> Bindable for GpuVec<T, Immutable> {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Buffer(self.buffer)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+}
+
+impl<T, Mut: Mutability> Drop for GpuVec<T, Mut> {
+    fn drop(&mut self) {
+        self.gpu.binding_manager.remove_resource(self.buffer);
+    }
+}
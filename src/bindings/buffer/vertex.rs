@@ -1,6 +1,9 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, ops::Range};
 
-use encase::{internal::WriteInto, DynamicStorageBuffer, ShaderSize, ShaderType, StorageBuffer};
+use encase::{
+    internal::{CreateFrom, WriteInto},
+    DynamicStorageBuffer, ShaderSize, ShaderType, StorageBuffer,
+};
 use parking_lot::MappedRwLockReadGuard;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
@@ -13,7 +16,7 @@ use crate::{
     misc::ids::BufferId,
 };
 
-use super::BufferBinding;
+use super::{download, BufferBinding, VertexBinding};
 
 /// Represents the vertices of a mesh for rendering.
 pub struct VertexBuffer<T> {
@@ -29,8 +32,6 @@ impl<T> VertexBuffer<T> {
         })
     }
 
-    // todo: make condensed vertex buffers that just bytemuck cast?
-
     pub fn upload(&self, data: &[T])
     where
         T: ShaderType + ShaderSize + WriteInto,
@@ -45,18 +46,75 @@ impl<T> VertexBuffer<T> {
             let replacement = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
                 label: None,
                 contents: &buffer,
-                usage: BufferUsages::COPY_DST | BufferUsages::VERTEX,
+                usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::VERTEX,
             });
 
             let binding_manager = &self.gpu.binding_manager;
             binding_manager.add_resource(self.buffer, replacement);
             binding_manager.mark_resource_dirty(&BindableResourceId::Buffer(self.buffer));
         } else {
-            self.gpu.queue.write_buffer(&this, 0, &buffer);
+            self.gpu.write_buffer(&this, 0, &buffer);
         }
     }
 }
 
+impl<T: ShaderType + ShaderSize + CreateFrom> VertexBuffer<T> {
+    /// Downloads every vertex in the buffer from the GPU in a blocking manner.
+    pub fn download(&self) -> Vec<T> {
+        let buffer = self.get();
+        download::create_array(download::download(&self.gpu, &buffer, 0..buffer.size()))
+    }
+
+    /// Requests the download of every vertex in the buffer. The provided
+    /// callback will be executed once the transfer finishes.
+    pub fn download_async(&self, func: impl FnOnce(Vec<T>) + Send + 'static) {
+        let buffer = self.get();
+        download::download_async(&self.gpu, &buffer, 0..buffer.size(), move |data| {
+            func(download::create_array(data))
+        });
+    }
+
+    /// Downloads the vertices in `range` from the GPU in a blocking manner.
+    pub fn download_range(&self, range: Range<u64>) -> Vec<T> {
+        let buffer = self.get();
+        let range = download::array_range::<T>(range, buffer.size());
+        download::create_array(download::download(&self.gpu, &buffer, range))
+    }
+
+    /// Requests the download of the vertices in `range`. The provided callback
+    /// will be executed once the transfer finishes.
+    pub fn download_range_async(
+        &self,
+        range: Range<u64>,
+        func: impl FnOnce(Vec<T>) + Send + 'static,
+    ) {
+        let buffer = self.get();
+        let range = download::array_range::<T>(range, buffer.size());
+        download::download_async(&self.gpu, &buffer, range, move |data| {
+            func(download::create_array(data))
+        });
+    }
+
+    /// Downloads the vertex at `index` from the GPU in a blocking manner.
+    pub fn download_element(&self, index: u64) -> T {
+        let buffer = self.get();
+        let range = download::element_range::<T>(index, buffer.size());
+        let mut store = DynamicStorageBuffer::new(download::download(&self.gpu, &buffer, range));
+        store.create().unwrap()
+    }
+
+    /// Requests the download of the vertex at `index`. The provided callback
+    /// will be executed once the transfer finishes.
+    pub fn download_element_async(&self, index: u64, func: impl FnOnce(T) + Send + 'static) {
+        let buffer = self.get();
+        let range = download::element_range::<T>(index, buffer.size());
+        download::download_async(&self.gpu, &buffer, range, move |data| {
+            let mut store = DynamicStorageBuffer::new(data);
+            func(store.create().unwrap());
+        });
+    }
+}
+
 impl Gpu {
     pub fn create_vertex<T>(&self, data: &[T]) -> VertexBuffer<T>
     where
@@ -69,7 +127,7 @@ impl Gpu {
         let id = BufferId::new();
         let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            usage: BufferUsages::COPY_DST | BufferUsages::VERTEX,
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::VERTEX,
             contents: &buffer,
         });
 
@@ -89,7 +147,7 @@ impl Gpu {
         let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
             size: (std::mem::size_of::<T>() * size) as u64,
-            usage: BufferUsages::COPY_DST | BufferUsages::VERTEX,
+            usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::VERTEX,
             mapped_at_creation: false,
         });
 
@@ -108,6 +166,8 @@ impl<T> BufferBinding for VertexBuffer<T> {
     }
 }
 
+impl<T> VertexBinding for VertexBuffer<T> {}
+
 impl<T> Bindable for VertexBuffer<T> {
     fn resource_id(&self) -> BindableResourceId {
         BindableResourceId::Buffer(self.buffer)
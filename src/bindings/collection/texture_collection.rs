@@ -13,6 +13,7 @@ use crate::{
 pub struct TextureCollection {
     gpu: Gpu,
     id: TextureCollectionId,
+    sample_type: TextureSampleType,
 }
 
 impl Gpu {
@@ -28,6 +29,7 @@ impl Gpu {
         TextureCollection {
             gpu: self.clone(),
             id,
+            sample_type: Format::sample_type(),
         }
     }
 }
@@ -39,7 +41,7 @@ impl Bindable for TextureCollection {
 
     fn binding_type(&self) -> BindingType {
         BindingType::Texture {
-            sample_type: TextureSampleType::Float { filterable: true },
+            sample_type: self.sample_type,
             view_dimension: TextureViewDimension::D2,
             multisampled: false,
         }
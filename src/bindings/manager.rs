@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, mem};
 
 use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
 use wgpu::{
@@ -6,7 +6,7 @@ use wgpu::{
 };
 
 use crate::{
-    misc::ids::{PipelineId, TextureCollectionId, TextureId},
+    misc::ids::{BufferId, PingPongId, PipelineId, TextureCollectionId, TextureId},
     pipeline::PipelineStatus,
 };
 
@@ -19,6 +19,14 @@ pub struct BindingManager {
     pipelines: RwMap<PipelineId, PipelineStatus>,
     resources: RwMap<BindableResourceId, BindableResource>,
     collections: RwMap<TextureCollectionId, Vec<TextureId>>,
+    ping_pongs: RwMap<PingPongId, PingPongStatus>,
+}
+
+/// The two buffers of a ping-pong pair. When not swapped, the first buffer is
+/// read from and the second written to.
+pub(crate) struct PingPongStatus {
+    pub buffers: [BufferId; 2],
+    pub swapped: bool,
 }
 
 impl BindingManager {
@@ -27,16 +35,81 @@ impl BindingManager {
             pipelines: RwLock::new(HashMap::new()),
             resources: RwLock::new(HashMap::new()),
             collections: RwLock::new(HashMap::new()),
+            ping_pongs: RwLock::new(HashMap::new()),
         }
     }
 
     pub(crate) fn mark_resource_dirty(&self, resource: &BindableResourceId) {
+        let ping_pongs = self.ping_pongs.read();
+        let uses_resource = |x: &BindableResourceId| match x {
+            // Pipelines can outlive the ping-pong pairs bound to them.
+            BindableResourceId::PingPong { id, .. } => ping_pongs.get(id).is_some_and(|status| {
+                status
+                    .buffers
+                    .iter()
+                    .any(|&buffer| BindableResourceId::Buffer(buffer) == *resource)
+            }),
+            x => x == resource,
+        };
+
         let mut pipelines = self.pipelines.write();
         for (_id, PipelineStatus { resources, dirty }) in pipelines.iter_mut() {
-            *dirty |= resources.contains(resource);
+            *dirty |= resources.iter().any(uses_resource);
+        }
+    }
+
+    /// Gets the resource that is currently bound for `id`. This is only
+    /// different from `id` for ping-pong buffers.
+    pub(crate) fn resolve(&self, id: BindableResourceId) -> BindableResourceId {
+        match id {
+            BindableResourceId::PingPong { id, write } => {
+                let ping_pongs = self.ping_pongs.read();
+                let status = ping_pongs
+                    .get(&id)
+                    .expect("PingPong was dropped while still bound to a pipeline");
+                BindableResourceId::Buffer(status.buffers[(write != status.swapped) as usize])
+            }
+            x => x,
         }
     }
 
+    /// Gets a bind group for the entries, reusing a cached one if it was made
+    /// with ping-pong buffers in their current state. The cache is cleared when
+    /// any of the resources are marked dirty.
+    pub(crate) fn bind_group(
+        &self,
+        device: &Device,
+        layout: &BindGroupLayout,
+        pipeline: PipelineId,
+        entries: &[BindableResourceId],
+        cache: &mut HashMap<Vec<bool>, BindGroup>,
+    ) -> BindGroup {
+        let dirty = mem::take(&mut self.pipelines.write().get_mut(&pipeline).unwrap().dirty);
+        if dirty {
+            cache.clear();
+        }
+
+        // Keyed by whether each ping-pong entry is swapped, in order.
+        let ping_pongs = self.ping_pongs.read();
+        let key = entries
+            .iter()
+            .filter_map(|x| match x {
+                BindableResourceId::PingPong { id, write } => Some(
+                    ping_pongs
+                        .get(id)
+                        .is_some_and(|status| status.swapped ^ write),
+                ),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        drop(ping_pongs);
+
+        cache
+            .entry(key)
+            .or_insert_with(|| self.create_bind_group(device, layout, entries))
+            .clone()
+    }
+
     pub(crate) fn create_bind_group(
         &self,
         device: &Device,
@@ -71,7 +144,7 @@ impl BindingManager {
                         collection_id += 1;
                         BindingResource::TextureViewArray(&collections[collection_id - 1])
                     }
-                    x => match &resources[x] {
+                    x => match &resources[&self.resolve(*x)] {
                         BindableResource::Buffer(buffer) => buffer.as_entire_binding(),
                         BindableResource::Texture(texture_view) => {
                             BindingResource::TextureView(texture_view)
@@ -98,10 +171,6 @@ impl BindingManager {
         self.pipelines.write().insert(id, status);
     }
 
-    pub(crate) fn get_pipeline(&self, id: PipelineId) -> MappedRwLockReadGuard<PipelineStatus> {
-        RwLockReadGuard::map(self.pipelines.read(), |x| &x[&id])
-    }
-
     pub(crate) fn remove_pipeline(&self, id: PipelineId) {
         self.pipelines.write().remove(&id);
     }
@@ -136,6 +205,25 @@ impl BindingManager {
         RwLockReadGuard::map(self.collections.read(), |x| &x[&id])
     }
 
+    pub(crate) fn add_ping_pong(&self, id: PingPongId, buffers: [BufferId; 2]) {
+        let status = PingPongStatus {
+            buffers,
+            swapped: false,
+        };
+        self.ping_pongs.write().insert(id, status);
+    }
+
+    pub(crate) fn swap_ping_pong(&self, id: PingPongId) -> bool {
+        let mut ping_pongs = self.ping_pongs.write();
+        let status = ping_pongs.get_mut(&id).unwrap();
+        status.swapped ^= true;
+        status.swapped
+    }
+
+    pub(crate) fn remove_ping_pong(&self, id: PingPongId) {
+        self.ping_pongs.write().remove(&id);
+    }
+
     pub(crate) fn renove_collection(&self, id: TextureCollectionId) {
         self.collections.write().remove(&id);
     }
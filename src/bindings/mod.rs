@@ -5,7 +5,7 @@ use std::num::NonZeroU32;
 use wgpu::{BindingType, Buffer, Sampler as WSampler, TextureView, TlasPackage};
 
 use crate::misc::ids::{
-    AccelerationStructureId, BufferId, SamplerId, TextureCollectionId, TextureId,
+    AccelerationStructureId, BufferId, PingPongId, SamplerId, TextureCollectionId, TextureId,
 };
 
 pub mod acceleration_structure;
@@ -13,6 +13,7 @@ pub mod buffer;
 pub mod collection;
 pub(crate) mod manager;
 pub mod texture;
+pub mod wgsl;
 
 /// A resource that can be bound to a pipline.
 pub trait Bindable {
@@ -23,6 +24,52 @@ pub trait Bindable {
     }
 }
 
+impl<T: Bindable + ?Sized> Bindable for &T {
+    fn resource_id(&self) -> BindableResourceId {
+        (**self).resource_id()
+    }
+
+    fn binding_type(&self) -> BindingType {
+        (**self).binding_type()
+    }
+
+    fn count(&self) -> Option<NonZeroU32> {
+        (**self).count()
+    }
+}
+
+/// A group of resources that are always bound together, in order. Usually
+/// derived with `#[derive(tufa::Bindings)]`, which requires the `derive`
+/// feature:
+///
+/// ```rust,ignore
+/// #[derive(Bindings)]
+/// struct Simulation<'a> {
+///     uniform: &'a UniformBuffer<Vector4<f32>>,
+///     #[binding(ty = "array<Particle>")]
+///     particles: &'a StorageBuffer<Vec<Particle>, Mutable>,
+///     background: &'a Texture<Rgba8>,
+/// }
+///
+/// let pipeline = gpu
+///     .compute_pipeline(include_wgsl!("simulate.wgsl"))
+///     .bind_all(&Simulation { .. })
+///     .finish();
+/// ```
+///
+/// Fields are bound in the order they are declared unless moved with
+/// `#[binding(n)]`. The WGSL type used in declarations can be set with
+/// `#[binding(ty = "...")]`, otherwise it comes from [`wgsl::WgslBinding`].
+/// The declarations can then be prepended to the shader source.
+pub trait Bindings {
+    /// The resources in the group, in binding order.
+    fn bindings(&self) -> Vec<&dyn Bindable>;
+
+    /// The WGSL declarations for every resource in the group, starting at
+    /// binding zero of group zero.
+    fn wgsl(&self) -> String;
+}
+
 /// The ID that maps to the [`BindableResource`] through the binding manager.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BindableResourceId {
@@ -32,6 +79,12 @@ pub enum BindableResourceId {
     AccelerationStructure(AccelerationStructureId),
 
     TextureCollection(TextureCollectionId),
+    /// One side of a [`PingPong`](buffer::PingPong) pair, resolved to a buffer
+    /// when bind groups are created.
+    PingPong {
+        id: PingPongId,
+        write: bool,
+    },
 }
 
 /// Any resource that can be bound to a pipline.
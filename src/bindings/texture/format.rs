@@ -1,23 +1,68 @@
 //! The pixel format of a texture.
 
+use wgpu::TextureSampleType;
+
 use crate::{DEPTH_TEXTURE_FORMAT, TEXTURE_FORMAT};
 
 pub trait TextureFormat {
     fn as_format() -> wgpu::TextureFormat;
+
+    /// The size of one texel, as laid out when copying to and from buffers, or
+    /// `None` if the texels can't be copied at all.
+    fn bytes_per_texel() -> Option<u32>;
+
+    /// How shaders sample the texture, including whether it can be filtered.
+    fn sample_type() -> TextureSampleType;
 }
 
-/// Rgba8Unorm
-pub struct Rgba8;
-impl TextureFormat for Rgba8 {
-    fn as_format() -> wgpu::TextureFormat {
-        TEXTURE_FORMAT
-    }
+macro_rules! formats {
+    ($($(#[$meta:meta])* $name:ident => $format:expr, $bytes:expr, $sample:expr;)*) => {
+        $(
+            $(#[$meta])*
+            pub struct $name;
+            impl TextureFormat for $name {
+                fn as_format() -> wgpu::TextureFormat {
+                    $format
+                }
+
+                fn bytes_per_texel() -> Option<u32> {
+                    $bytes
+                }
+
+                fn sample_type() -> TextureSampleType {
+                    $sample
+                }
+            }
+        )*
+    };
 }
 
-/// Depth24PlusStencil8
-pub struct Depth;
-impl TextureFormat for Depth {
-    fn as_format() -> wgpu::TextureFormat {
-        DEPTH_TEXTURE_FORMAT
-    }
+const FILTERABLE: TextureSampleType = TextureSampleType::Float { filterable: true };
+const UNFILTERABLE: TextureSampleType = TextureSampleType::Float { filterable: false };
+
+formats! {
+    /// Rgba8Unorm
+    Rgba8 => TEXTURE_FORMAT, Some(4), FILTERABLE;
+    /// Rgba8UnormSrgb
+    Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb, Some(4), FILTERABLE;
+    /// Bgra8Unorm
+    Bgra8 => wgpu::TextureFormat::Bgra8Unorm, Some(4), FILTERABLE;
+    /// R8Unorm
+    R8 => wgpu::TextureFormat::R8Unorm, Some(1), FILTERABLE;
+    /// Rgba16Float
+    Rgba16Float => wgpu::TextureFormat::Rgba16Float, Some(8), FILTERABLE;
+    /// R32Float. Can't be filtered without the `FLOAT32_FILTERABLE` feature,
+    /// so it must be sampled with a non-filtering sampler.
+    R32Float => wgpu::TextureFormat::R32Float, Some(4), UNFILTERABLE;
+    /// Rg32Float. Can't be filtered, like [`R32Float`].
+    Rg32Float => wgpu::TextureFormat::Rg32Float, Some(8), UNFILTERABLE;
+    /// Rgba32Float. Can't be filtered, like [`R32Float`].
+    Rgba32Float => wgpu::TextureFormat::Rgba32Float, Some(16), UNFILTERABLE;
+    /// R32Uint
+    R32Uint => wgpu::TextureFormat::R32Uint, Some(4), TextureSampleType::Uint;
+    /// Depth24PlusStencil8. Its texels can't be copied to or from buffers.
+    Depth => DEPTH_TEXTURE_FORMAT, None, TextureSampleType::Depth;
+    /// Depth32Float. Its texels can be copied to buffers and downloaded, but
+    /// not uploaded.
+    Depth32Float => wgpu::TextureFormat::Depth32Float, Some(4), TextureSampleType::Depth;
 }
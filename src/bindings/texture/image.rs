@@ -0,0 +1,235 @@
+//! Converting between textures and images from the [`image`] crate.
+//! Requires the `image` feature.
+//!
+//! The texture's format decides how an image is converted, and colors are
+//! never converted between color spaces:
+//!
+//! - [`Rgba8UnormSrgb`] is the right choice for most color images, which are
+//!   stored sRGB encoded. Shaders will sample linear values.
+//! - [`Rgba8`] and [`Bgra8`] keep the stored values as is, for data like normal
+//!   maps. Color images will be sampled still sRGB encoded.
+//! - [`Rgba16Float`] and [`Rgba32Float`] keep the precision of 16 bit and
+//!   float images, and also keep the stored values. 8 and 16 bit images are
+//!   normalized to the range 0 to 1.
+//! - [`R8`] and [`R32Float`] convert images to grayscale.
+//!
+//! ```rust,ignore
+//! let albedo = gpu.load_texture::<Rgba8UnormSrgb>("brick.png")?;
+//! let heights = gpu.load_texture::<R32Float>("heights.exr")?;
+//! ```
+//!
+//! When the format isn't known ahead of time, [`Gpu::load_image_texture`]
+//! picks one that matches the file instead, returning an [`ImageTexture`].
+//! Loading a 16 bit PNG as [`Rgba8`] would throw away half its precision, so
+//! it is loaded as [`Rgba16Float`].
+
+use std::path::Path;
+
+use ::image::{DynamicImage, GrayImage, Rgb32FImage, Rgba32FImage, RgbaImage};
+use anyhow::{Context, Result};
+use half::f16;
+use nalgebra::Vector2;
+
+use crate::gpu::Gpu;
+
+use super::{
+    format::{Bgra8, R32Float, Rgba16Float, Rgba32Float, Rgba8, Rgba8UnormSrgb, TextureFormat, R8},
+    Texture,
+};
+
+/// A texture format that images can be converted to and from.
+pub trait ImageTextureFormat: TextureFormat {
+    /// Converts the image into tightly packed texels of this format.
+    fn from_image(image: &DynamicImage) -> Vec<u8>;
+
+    /// Creates an image from tightly packed texels of this format.
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage;
+}
+
+impl ImageTextureFormat for Rgba8 {
+    fn from_image(image: &DynamicImage) -> Vec<u8> {
+        image.to_rgba8().into_raw()
+    }
+
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage {
+        RgbaImage::from_raw(size.x, size.y, data).unwrap().into()
+    }
+}
+
+impl ImageTextureFormat for Rgba8UnormSrgb {
+    fn from_image(image: &DynamicImage) -> Vec<u8> {
+        Rgba8::from_image(image)
+    }
+
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage {
+        Rgba8::to_image(size, data)
+    }
+}
+
+impl ImageTextureFormat for Bgra8 {
+    fn from_image(image: &DynamicImage) -> Vec<u8> {
+        swap_red_blue(Rgba8::from_image(image))
+    }
+
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage {
+        Rgba8::to_image(size, swap_red_blue(data))
+    }
+}
+
+impl ImageTextureFormat for R8 {
+    fn from_image(image: &DynamicImage) -> Vec<u8> {
+        image.to_luma8().into_raw()
+    }
+
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage {
+        GrayImage::from_raw(size.x, size.y, data).unwrap().into()
+    }
+}
+
+impl ImageTextureFormat for Rgba16Float {
+    fn from_image(image: &DynamicImage) -> Vec<u8> {
+        (image.to_rgba32f().iter())
+            .flat_map(|x| f16::from_f32(*x).to_le_bytes())
+            .collect()
+    }
+
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage {
+        let data = (data.chunks_exact(2))
+            .map(|x| f16::from_le_bytes([x[0], x[1]]).to_f32())
+            .collect();
+        Rgba32FImage::from_raw(size.x, size.y, data).unwrap().into()
+    }
+}
+
+impl ImageTextureFormat for Rgba32Float {
+    fn from_image(image: &DynamicImage) -> Vec<u8> {
+        bytemuck::cast_slice(&image.to_rgba32f()).to_vec()
+    }
+
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage {
+        let data = bytemuck::pod_collect_to_vec(&data);
+        Rgba32FImage::from_raw(size.x, size.y, data).unwrap().into()
+    }
+}
+
+impl ImageTextureFormat for R32Float {
+    fn from_image(image: &DynamicImage) -> Vec<u8> {
+        bytemuck::cast_slice(&image.to_luma32f()).to_vec()
+    }
+
+    /// As there is no single channel float image type, the image is gray RGB.
+    fn to_image(size: Vector2<u32>, data: Vec<u8>) -> DynamicImage {
+        let data = (bytemuck::pod_collect_to_vec::<u8, f32>(&data).into_iter())
+            .flat_map(|x| [x; 3])
+            .collect();
+        Rgb32FImage::from_raw(size.x, size.y, data).unwrap().into()
+    }
+}
+
+/// A texture in the format that best matches the image it was created from.
+pub enum ImageTexture {
+    /// 8 bit color images, which are stored sRGB encoded.
+    Rgba8UnormSrgb(Texture<Rgba8UnormSrgb>),
+    /// 8 bit grayscale images.
+    R8(Texture<R8>),
+    /// 16 bit images, normalized to the range 0 to 1. Their values are kept
+    /// as stored, so color images are still sRGB encoded.
+    Rgba16Float(Texture<Rgba16Float>),
+    /// Float images, like OpenEXR and HDR files.
+    Rgba32Float(Texture<Rgba32Float>),
+}
+
+impl ImageTexture {
+    /// Downloads the texture into an image.
+    pub fn to_image(&self) -> DynamicImage {
+        match self {
+            Self::Rgba8UnormSrgb(texture) => texture.to_image(),
+            Self::R8(texture) => texture.to_image(),
+            Self::Rgba16Float(texture) => texture.to_image(),
+            Self::Rgba32Float(texture) => texture.to_image(),
+        }
+    }
+
+    /// Downloads the texture and saves it to an image file. See
+    /// [`Texture::save`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        match self {
+            Self::Rgba8UnormSrgb(texture) => texture.save(path),
+            Self::R8(texture) => texture.save(path),
+            Self::Rgba16Float(texture) => texture.save(path),
+            Self::Rgba32Float(texture) => texture.save(path),
+        }
+    }
+}
+
+fn swap_red_blue(mut data: Vec<u8>) -> Vec<u8> {
+    data.chunks_exact_mut(4).for_each(|x| x.swap(0, 2));
+    data
+}
+
+impl<Format: ImageTextureFormat> Texture<Format> {
+    /// Downloads the texture into an image. Only 2D textures are supported.
+    pub fn to_image(&self) -> DynamicImage {
+        assert_eq!(
+            self.size.z, 1,
+            "Only 2D textures can be converted to images"
+        );
+        Format::to_image(self.size.xy(), self.download())
+    }
+
+    /// Downloads the texture and saves it to an image file, with the format
+    /// picked from the extension. Float textures need a file format that
+    /// supports them, like OpenEXR.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        (self.to_image().save(path)).with_context(|| format!("Failed to save {}", path.display()))
+    }
+}
+
+impl Gpu {
+    /// Creates a texture from an image, converting it to the texture's format.
+    pub fn texture_from_image<Format: ImageTextureFormat>(
+        &self,
+        image: &DynamicImage,
+    ) -> Texture<Format> {
+        let texture = self.create_texture_2d(Vector2::new(image.width(), image.height()));
+        texture.upload(&Format::from_image(image));
+        texture
+    }
+
+    /// Creates a texture from an image, in the format that matches it best.
+    pub fn image_texture(&self, image: &DynamicImage) -> ImageTexture {
+        match image {
+            DynamicImage::ImageLuma8(_) => ImageTexture::R8(self.texture_from_image(image)),
+            DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => {
+                ImageTexture::Rgba16Float(self.texture_from_image(image))
+            }
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                ImageTexture::Rgba32Float(self.texture_from_image(image))
+            }
+            _ => ImageTexture::Rgba8UnormSrgb(self.texture_from_image(image)),
+        }
+    }
+
+    /// Loads an image file into a texture, in the format that matches it
+    /// best.
+    pub fn load_image_texture(&self, path: impl AsRef<Path>) -> Result<ImageTexture> {
+        Ok(self.image_texture(&open(path.as_ref())?))
+    }
+
+    /// Loads an image file into a texture, converting it to the texture's
+    /// format.
+    pub fn load_texture<Format: ImageTextureFormat>(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Texture<Format>> {
+        Ok(self.texture_from_image(&open(path.as_ref())?))
+    }
+}
+
+fn open(path: &Path) -> Result<DynamicImage> {
+    ::image::open(path).with_context(|| format!("Failed to load {}", path.display()))
+}
@@ -0,0 +1,132 @@
+//! Generating mipmaps, so minified textures don't shimmer.
+
+use nalgebra::Vector2;
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindingResource, FilterMode, FragmentState, LoadOp,
+    Operations, PipelineCompilationOptions, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipelineDescriptor, SamplerDescriptor, StoreOp, TextureSampleType, TextureUsages,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+use crate::gpu::Gpu;
+
+use super::{format::TextureFormat, Texture};
+
+impl<Format: TextureFormat> Texture<Format> {
+    /// The number of mip levels the texture has.
+    pub fn mip_levels(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+
+    /// Fills every mip level after the first by downsampling the one before
+    /// it. Call this after uploading or rendering into the texture. Panics if
+    /// the format can't be both filtered and rendered to.
+    pub fn generate_mipmaps(&self) {
+        let format = Format::as_format();
+        assert!(
+            Format::sample_type() == TextureSampleType::Float { filterable: true }
+                && self
+                    .texture
+                    .usage()
+                    .contains(TextureUsages::RENDER_ATTACHMENT),
+            "Mipmaps can't be generated for {format:?} textures"
+        );
+        assert_eq!(
+            self.size.z, 1,
+            "Mipmaps can only be generated for 2D textures"
+        );
+
+        let device = &self.gpu.device;
+        let source = include_str!("mipmap.wgsl");
+        let cache = &self.gpu.shader_cache;
+        let pipeline = cache.render_pipeline(source, format, || {
+            let module = cache.module(device, source);
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("tufa mipmaps"),
+                layout: None,
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: Some("vert"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &module,
+                    entry_point: Some("frag"),
+                    targets: &[Some(format.into())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let views = (0..self.mip_levels())
+            .map(|level| {
+                self.texture.create_view(&TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        self.gpu.immediate_dispatch(|encoder| {
+            for levels in views.windows(2) {
+                let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                    label: None,
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&levels[0]),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&sampler),
+                        },
+                    ],
+                });
+
+                let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("tufa mipmaps"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &levels[1],
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Load,
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    ..Default::default()
+                });
+
+                render_pass.set_pipeline(&pipeline);
+                render_pass.set_bind_group(0, Some(&bind_group), &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        });
+    }
+}
+
+impl Gpu {
+    /// Creates a 2D texture with a full chain of mip levels, down to a single
+    /// texel. Only the first level is written by [`Texture::upload`], so call
+    /// [`Texture::generate_mipmaps`] afterwards.
+    pub fn create_texture_2d_with_mips<Format: TextureFormat>(
+        &self,
+        size: Vector2<u32>,
+    ) -> Texture<Format> {
+        let levels = u32::BITS - size.x.max(size.y).max(1).leading_zeros();
+        self.create_texture(size.push(1), TextureViewDimension::D2, levels)
+    }
+}
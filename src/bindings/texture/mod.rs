@@ -1,39 +1,75 @@
 //! Textures are like buffers optimized for image data and with automatic interpolation.
 
-use std::marker::PhantomData;
+use std::{marker::PhantomData, ops::Range};
 
 use format::TextureFormat;
 use nalgebra::{Vector2, Vector3};
 use wgpu::{
-    BindingType, Extent3d, Origin3d, TexelCopyBufferInfo, TexelCopyBufferLayout,
-    TexelCopyTextureInfo, TextureAspect, TextureDescriptor, TextureDimension, TextureSampleType,
-    TextureUsages, TextureViewDescriptor, TextureViewDimension,
+    BindingType, Buffer, DownlevelFlags, Extent3d, Origin3d, StorageTextureAccess,
+    TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 
 use crate::{gpu::Gpu, misc::ids::TextureId};
 
-use super::{buffer::BufferBinding, Bindable, BindableResourceId};
+use super::{
+    buffer::{download, BufferBinding},
+    Bindable, BindableResourceId,
+};
 
 pub mod format;
+#[cfg(feature = "image")]
+pub mod image;
+mod mips;
 mod sampler;
-pub use sampler::Sampler;
+mod storage;
+pub use sampler::{Sampler, SamplerBuilder};
+pub use storage::StorageTexture;
 
-/// A 2D texture.
+/// A 2D or 3D texture, array of 2D textures, or cube map.
 pub struct Texture<Format: TextureFormat> {
     gpu: Gpu,
 
     pub(crate) id: TextureId,
     texture: wgpu::Texture,
-    size: Vector3<u32>,
+    /// The width, height, and either depth or number of array layers.
+    pub(crate) size: Vector3<u32>,
+    view_dimension: TextureViewDimension,
 
     _format: PhantomData<Format>,
 }
 
 impl<Format: TextureFormat> Texture<Format> {
+    /// Gets a binding to the texture as a storage texture, which lets compute
+    /// shaders read and write texels directly. Panics if the texture's format
+    /// doesn't support the requested access, or if the texture has mipmaps.
+    ///
+    /// ```wgsl
+    /// @group(0) @binding(0) var output: texture_storage_2d<rgba8unorm, write>;
+    /// ```
+    pub fn storage(&self, access: StorageTextureAccess) -> StorageTexture<'_, Format> {
+        StorageTexture::new(self, access)
+    }
+
+    pub(crate) fn view_dimension(&self) -> TextureViewDimension {
+        self.view_dimension
+    }
+
+    /// The number of layers in the texture. This is the depth of 3D textures,
+    /// the number of array layers of 2D arrays, and six times the number of
+    /// cubes for cube maps.
+    pub fn layers(&self) -> u32 {
+        self.size.z
+    }
+
+    /// Uploads the texels of every layer. Panics for depth textures, which
+    /// can't be written from the CPU.
     pub fn upload(&self, data: &[u8]) {
+        let bytes_per_texel = Self::upload_bytes_per_texel();
         assert_eq!(
             data.len(),
-            self.size.iter().copied().product::<u32>() as usize * 4
+            self.size.iter().copied().product::<u32>() as usize * bytes_per_texel as usize
         );
 
         self.gpu.queue.write_texture(
@@ -41,81 +77,330 @@ impl<Format: TextureFormat> Texture<Format> {
             data,
             TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(self.size.x * 4),
+                bytes_per_row: Some(self.size.x * bytes_per_texel),
+                rows_per_image: Some(self.size.y),
+            },
+            self.extent(),
+        );
+    }
+
+    /// Uploads the texels of a single layer, or a single depth slice of a 3D
+    /// texture. The faces of a cube map are layers in the order +X, -X, +Y,
+    /// -Y, +Z, -Z. Panics for depth textures, like [`Texture::upload`].
+    pub fn upload_layer(&self, layer: u32, data: &[u8]) {
+        self.assert_layer(layer);
+        let bytes_per_texel = Self::upload_bytes_per_texel();
+        assert_eq!(
+            data.len(),
+            self.size.xy().iter().copied().product::<u32>() as usize * bytes_per_texel as usize
+        );
+
+        self.gpu.queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            data,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(self.size.x * bytes_per_texel),
                 rows_per_image: Some(self.size.y),
             },
             Extent3d {
-                width: self.size.x,
-                height: self.size.y,
-                depth_or_array_layers: self.size.z,
+                depth_or_array_layers: 1,
+                ..self.extent()
             },
         );
     }
 
+    /// Downloads the texture's texels in a blocking manner. Rows are tightly
+    /// packed, and layers follow each other.
+    pub fn download(&self) -> Vec<u8> {
+        self.download_layers(0..self.size.z)
+    }
+
+    /// Downloads the texels of a single layer in a blocking manner. See
+    /// [`Texture::upload_layer`] for the order of cube map faces.
+    pub fn download_layer(&self, layer: u32) -> Vec<u8> {
+        self.assert_layer(layer);
+        self.download_layers(layer..layer + 1)
+    }
+
+    /// Requests the download of the texture's texels. The provided callback
+    /// will be executed once the transfer finishes.
+    pub fn download_async(&self, func: impl FnOnce(Vec<u8>) + Send + 'static) {
+        let layers = 0..self.size.z;
+        let layout = self.row_layout(layers.len() as u32);
+        let staging = self.copy_to_staging(layers);
+        download::read_staging_async(&self.gpu, staging, move |data| layout.unpad(data), func);
+    }
+
+    fn download_layers(&self, layers: Range<u32>) -> Vec<u8> {
+        let layout = self.row_layout(layers.len() as u32);
+        let staging = self.copy_to_staging(layers);
+        download::read_staging(&self.gpu, staging, |data| layout.unpad(data))
+    }
+
+    fn assert_layer(&self, layer: u32) {
+        assert!(
+            layer < self.size.z,
+            "Layer {layer} is out of bounds for a texture with {} layers",
+            self.size.z
+        );
+    }
+
+    /// A view of the first mip level and layer, for rendering into the
+    /// texture.
+    pub(crate) fn attachment(&self) -> TextureView {
+        assert_ne!(
+            self.view_dimension,
+            TextureViewDimension::D3,
+            "3D textures can't be rendered into"
+        );
+
+        self.texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2),
+            mip_level_count: Some(1),
+            array_layer_count: Some(1),
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn extent(&self) -> Extent3d {
+        Extent3d {
+            width: self.size.x,
+            height: self.size.y,
+            depth_or_array_layers: self.size.z,
+        }
+    }
+
+    /// Copies the texture into a buffer. As wgpu requires, each row starts at a
+    /// multiple of [`COPY_BYTES_PER_ROW_ALIGNMENT`] bytes, so rows are padded
+    /// unless the texture's width in bytes is already a multiple of it.
+    /// Shaders reading the buffer should index rows by the padded width.
+    ///
+    /// The buffer must hold at least the padded row size times the number of
+    /// rows in every layer.
     pub fn copy_to_buffer<T: BufferBinding>(&self, buffer: &T) {
+        let layout = self.row_layout(self.size.z);
+        let size = layout.padded as u64 * layout.rows as u64;
+
         let buffer = self.gpu.binding_manager.get_resource(buffer.get_id());
         let buffer = buffer.expect_buffer();
+        assert!(
+            buffer.size() >= size,
+            "Buffer of {} bytes is too small to hold the texture's {size} bytes",
+            buffer.size()
+        );
+
+        self.copy_to(buffer, 0..self.size.z);
+    }
 
+    fn copy_to_staging(&self, layers: Range<u32>) -> Buffer {
+        let layout = self.row_layout(layers.len() as u32);
+        let size = layout.padded as u64 * layout.rows as u64;
+
+        let staging = self.gpu.staging.take(&self.gpu.device, size);
+        self.copy_to(&staging, layers);
+        staging
+    }
+
+    fn copy_to(&self, buffer: &Buffer, layers: Range<u32>) {
+        let bytes_per_row = self.row_layout(1).padded;
         self.gpu.immediate_dispatch(|encoder| {
             encoder.copy_texture_to_buffer(
                 TexelCopyTextureInfo {
                     texture: &self.texture,
                     mip_level: 0,
-                    origin: Origin3d::ZERO,
+                    origin: Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layers.start,
+                    },
                     aspect: TextureAspect::All,
                 },
                 TexelCopyBufferInfo {
                     buffer,
                     layout: TexelCopyBufferLayout {
                         offset: 0,
-                        bytes_per_row: Some(self.size.x * 4),
+                        bytes_per_row: Some(bytes_per_row),
                         rows_per_image: Some(self.size.y),
                     },
                 },
                 Extent3d {
-                    width: self.size.x,
-                    height: self.size.y,
-                    depth_or_array_layers: self.size.z,
+                    depth_or_array_layers: layers.len() as u32,
+                    ..self.extent()
                 },
             );
         });
     }
+
+    fn bytes_per_texel() -> u32 {
+        Format::bytes_per_texel().unwrap_or_else(|| {
+            panic!(
+                "{:?} textures can't be copied to or from buffers",
+                Format::as_format()
+            )
+        })
+    }
+
+    /// Like [`Self::bytes_per_texel`], but also rejects depth formats, whose
+    /// texels can be copied to buffers but never written to the texture.
+    fn upload_bytes_per_texel() -> u32 {
+        let format = Format::as_format();
+        assert!(
+            !format.has_depth_aspect(),
+            "{format:?} textures can't be uploaded to, only rendered into"
+        );
+        Self::bytes_per_texel()
+    }
+
+    fn row_layout(&self, layers: u32) -> RowLayout {
+        let row = self.size.x * Self::bytes_per_texel();
+        RowLayout {
+            row,
+            padded: row.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT),
+            rows: self.size.y * layers,
+        }
+    }
+}
+
+/// How texel rows are laid out when copied into a buffer.
+#[derive(Clone, Copy)]
+struct RowLayout {
+    row: u32,
+    padded: u32,
+    rows: u32,
+}
+
+impl RowLayout {
+    /// Removes the padding from the end of each row.
+    fn unpad(&self, data: &[u8]) -> Vec<u8> {
+        data.chunks(self.padded as usize)
+            .take(self.rows as usize)
+            .flat_map(|x| &x[..self.row as usize])
+            .copied()
+            .collect()
+    }
 }
 
 impl Gpu {
     pub fn create_texture_2d<Format: TextureFormat>(&self, size: Vector2<u32>) -> Texture<Format> {
+        self.create_texture(size.push(1), TextureViewDimension::D2, 1)
+    }
+
+    /// Creates a 3D texture, bound as a `texture_3d`.
+    pub fn create_texture_3d<Format: TextureFormat>(&self, size: Vector3<u32>) -> Texture<Format> {
+        self.create_texture(size, TextureViewDimension::D3, 1)
+    }
+
+    /// Creates an array of 2D textures with the same size, bound as a
+    /// `texture_2d_array`.
+    pub fn create_texture_2d_array<Format: TextureFormat>(
+        &self,
+        size: Vector2<u32>,
+        layers: u32,
+    ) -> Texture<Format> {
+        self.create_texture(size.push(layers), TextureViewDimension::D2Array, 1)
+    }
+
+    /// Creates a cube map with square faces `size` texels wide, bound as a
+    /// `texture_cube`.
+    pub fn create_cube_texture<Format: TextureFormat>(&self, size: u32) -> Texture<Format> {
+        self.create_texture(Vector3::new(size, size, 6), TextureViewDimension::Cube, 1)
+    }
+
+    /// Creates an array of `count` cube maps, bound as a `texture_cube_array`.
+    /// Cube `i` is made of layers `6 * i` to `6 * i + 5`. Panics if the device
+    /// doesn't support cube arrays.
+    pub fn create_cube_texture_array<Format: TextureFormat>(
+        &self,
+        size: u32,
+        count: u32,
+    ) -> Texture<Format> {
+        assert!(
+            (self.adapter.get_downlevel_capabilities().flags)
+                .contains(DownlevelFlags::CUBE_ARRAY_TEXTURES),
+            "The device doesn't support cube array textures"
+        );
+        self.create_texture(
+            Vector3::new(size, size, 6 * count),
+            TextureViewDimension::CubeArray,
+            1,
+        )
+    }
+
+    fn create_texture<Format: TextureFormat>(
+        &self,
+        size: Vector3<u32>,
+        view_dimension: TextureViewDimension,
+        mip_levels: u32,
+    ) -> Texture<Format> {
+        let dimension = match view_dimension {
+            TextureViewDimension::D3 => TextureDimension::D3,
+            _ => TextureDimension::D2,
+        };
+
         let texture = self.device.create_texture(&TextureDescriptor {
             label: None,
             size: Extent3d {
                 width: size.x,
                 height: size.y,
-                depth_or_array_layers: 1,
+                depth_or_array_layers: size.z,
             },
-            mip_level_count: 1,
+            mip_level_count: mip_levels,
             sample_count: 1,
-            dimension: TextureDimension::D2,
+            dimension,
             format: Format::as_format(),
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_DST
-                | TextureUsages::RENDER_ATTACHMENT
-                | TextureUsages::COPY_SRC,
+            usage: self.texture_usages(Format::as_format(), dimension),
             view_formats: &[],
         });
 
         let id = TextureId::new();
-        let view = texture.create_view(&TextureViewDescriptor::default());
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
 
         self.binding_manager.add_resource(id, view);
         Texture {
             gpu: self.clone(),
             id,
             texture,
-            size: Vector3::new(size.x, size.y, 1),
+            size,
+            view_dimension,
             _format: PhantomData,
         }
     }
 }
 
+impl Gpu {
+    /// Every usage tufa textures are created with, plus render attachment and
+    /// storage binding when the format supports them. 3D textures can't be
+    /// rendered into.
+    fn texture_usages(
+        &self,
+        format: wgpu::TextureFormat,
+        dimension: TextureDimension,
+    ) -> TextureUsages {
+        let usages =
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC;
+
+        let mut allowed = self.texture_format_features(format).allowed_usages;
+        if dimension == TextureDimension::D3 {
+            allowed.remove(TextureUsages::RENDER_ATTACHMENT);
+        }
+
+        usages | (allowed & (TextureUsages::RENDER_ATTACHMENT | TextureUsages::STORAGE_BINDING))
+    }
+}
+
 impl<Format: TextureFormat> Bindable for Texture<Format> {
     fn resource_id(&self) -> BindableResourceId {
         BindableResourceId::Texture(self.id)
@@ -123,12 +408,8 @@ impl<Format: TextureFormat> Bindable for Texture<Format> {
 
     fn binding_type(&self) -> BindingType {
         BindingType::Texture {
-            sample_type: TextureSampleType::Float { filterable: true },
-            view_dimension: if self.size.z > 1 {
-                TextureViewDimension::D3
-            } else {
-                TextureViewDimension::D2
-            },
+            sample_type: Format::sample_type(),
+            view_dimension: self.view_dimension(),
             multisampled: false,
         }
     }
@@ -139,3 +420,24 @@ impl<Format: TextureFormat> Bindable for Texture<Format> {
 //         self.gpu.binding_manager.remove_resource(self.id);
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpad_rows() {
+        // An R8 texture that is 3 texels wide and 2 tall.
+        let layout = RowLayout {
+            row: 3,
+            padded: COPY_BYTES_PER_ROW_ALIGNMENT,
+            rows: 2,
+        };
+
+        let padded = COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+        let mut data = vec![0xff; 2 * padded];
+        data[..3].copy_from_slice(&[1, 2, 3]);
+        data[padded..padded + 3].copy_from_slice(&[4, 5, 6]);
+        assert_eq!(layout.unpad(&data), [1, 2, 3, 4, 5, 6]);
+    }
+}
@@ -1,6 +1,11 @@
 //! Texture sampler.
 
-use wgpu::{AddressMode, BindingType, FilterMode, SamplerBindingType, SamplerDescriptor};
+use std::ops::Range;
+
+use wgpu::{
+    AddressMode, BindingType, CompareFunction, FilterMode, SamplerBindingType, SamplerBorderColor,
+    SamplerDescriptor,
+};
 
 use crate::{gpu::Gpu, misc::ids::SamplerId};
 
@@ -10,33 +15,170 @@ pub struct Sampler {
     gpu: Gpu,
 
     pub(crate) id: SamplerId,
+    binding_type: SamplerBindingType,
 }
 
-impl Gpu {
-    pub fn create_sampler(&self, mode: FilterMode) -> Sampler {
-        let sampler = self.device.create_sampler(&SamplerDescriptor {
-            label: None,
-            address_mode_u: AddressMode::Repeat,
-            address_mode_v: AddressMode::Repeat,
-            address_mode_w: AddressMode::Repeat,
-            mag_filter: mode,
-            min_filter: mode,
-            mipmap_filter: mode,
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 0.0,
-            compare: None,
-            anisotropy_clamp: 1,
-            border_color: None,
-        });
+/// Builds a [`Sampler`] with control over how textures are addressed,
+/// filtered, and compared:
+///
+/// ```rust,ignore
+/// let shadow = gpu
+///     .sampler()
+///     .address_mode(AddressMode::ClampToEdge)
+///     .filter(FilterMode::Linear)
+///     .compare(CompareFunction::LessEqual)
+///     .build();
+/// ```
+pub struct SamplerBuilder {
+    gpu: Gpu,
+    descriptor: SamplerDescriptor<'static>,
+}
+
+impl SamplerBuilder {
+    /// Sets how coordinates outside of the texture are handled on every axis.
+    pub fn address_mode(self, mode: AddressMode) -> Self {
+        self.address_mode_u(mode)
+            .address_mode_v(mode)
+            .address_mode_w(mode)
+    }
+
+    /// Sets how horizontal coordinates outside of the texture are handled.
+    pub fn address_mode_u(mut self, mode: AddressMode) -> Self {
+        self.descriptor.address_mode_u = mode;
+        self
+    }
+
+    /// Sets how vertical coordinates outside of the texture are handled.
+    pub fn address_mode_v(mut self, mode: AddressMode) -> Self {
+        self.descriptor.address_mode_v = mode;
+        self
+    }
+
+    /// Sets how depth coordinates outside of 3D textures are handled.
+    pub fn address_mode_w(mut self, mode: AddressMode) -> Self {
+        self.descriptor.address_mode_w = mode;
+        self
+    }
+
+    /// Sets the color returned outside of the texture on axes using
+    /// [`AddressMode::ClampToBorder`], which requires the
+    /// `ADDRESS_MODE_CLAMP_TO_BORDER` feature.
+    pub fn border_color(mut self, color: SamplerBorderColor) -> Self {
+        self.descriptor.border_color = Some(color);
+        self
+    }
+
+    /// Sets the magnification, minification, and mipmap filters at once.
+    pub fn filter(self, mode: FilterMode) -> Self {
+        self.mag_filter(mode).min_filter(mode).mipmap_filter(mode)
+    }
+
+    /// Sets how the texture is filtered when a texel covers many pixels.
+    pub fn mag_filter(mut self, mode: FilterMode) -> Self {
+        self.descriptor.mag_filter = mode;
+        self
+    }
+
+    /// Sets how the texture is filtered when a pixel covers many texels.
+    pub fn min_filter(mut self, mode: FilterMode) -> Self {
+        self.descriptor.min_filter = mode;
+        self
+    }
 
+    /// Sets how samples from neighboring mip levels are blended.
+    pub fn mipmap_filter(mut self, mode: FilterMode) -> Self {
+        self.descriptor.mipmap_filter = mode;
+        self
+    }
+
+    /// Clamps the mip level sampled from to the range.
+    pub fn lod_range(mut self, range: Range<f32>) -> Self {
+        self.descriptor.lod_min_clamp = range.start;
+        self.descriptor.lod_max_clamp = range.end;
+        self
+    }
+
+    /// Sets the maximum anisotropy, from 1 to 16. Values above 1 require
+    /// every filter to be [`FilterMode::Linear`].
+    pub fn anisotropy(mut self, anisotropy: u16) -> Self {
+        self.descriptor.anisotropy_clamp = anisotropy;
+        self
+    }
+
+    /// Makes this a comparison sampler, which compares samples of a depth
+    /// texture against a reference value, as used for shadow mapping. It will
+    /// be bound as a `sampler_comparison`.
+    pub fn compare(mut self, function: CompareFunction) -> Self {
+        self.descriptor.compare = Some(function);
+        self
+    }
+
+    /// Creates the sampler.
+    pub fn build(self) -> Sampler {
+        let descriptor = &self.descriptor;
+        let filters = [
+            descriptor.mag_filter,
+            descriptor.min_filter,
+            descriptor.mipmap_filter,
+        ];
+
+        assert!(
+            (1..=16).contains(&descriptor.anisotropy_clamp),
+            "Anisotropy must be between 1 and 16"
+        );
+        assert!(
+            descriptor.anisotropy_clamp == 1 || filters.iter().all(|x| *x == FilterMode::Linear),
+            "Anisotropic filtering requires every filter to be linear"
+        );
+
+        let binding_type = if descriptor.compare.is_some() {
+            SamplerBindingType::Comparison
+        } else if filters.iter().all(|x| *x == FilterMode::Nearest) {
+            SamplerBindingType::NonFiltering
+        } else {
+            SamplerBindingType::Filtering
+        };
+
+        let sampler = self.gpu.device.create_sampler(descriptor);
         let id = SamplerId::new();
-        self.binding_manager.add_resource(id, sampler);
+        self.gpu.binding_manager.add_resource(id, sampler);
 
         Sampler {
-            gpu: self.clone(),
+            gpu: self.gpu,
             id,
+            binding_type,
+        }
+    }
+}
+
+impl Gpu {
+    /// Starts building a sampler. Textures repeat on every axis, are filtered
+    /// with [`FilterMode::Nearest`], and every mip level can be sampled from
+    /// unless changed.
+    pub fn sampler(&self) -> SamplerBuilder {
+        SamplerBuilder {
+            gpu: self.clone(),
+            descriptor: SamplerDescriptor {
+                label: None,
+                address_mode_u: AddressMode::Repeat,
+                address_mode_v: AddressMode::Repeat,
+                address_mode_w: AddressMode::Repeat,
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                mipmap_filter: FilterMode::Nearest,
+                lod_min_clamp: 0.0,
+                lod_max_clamp: 32.0,
+                compare: None,
+                anisotropy_clamp: 1,
+                border_color: None,
+            },
         }
     }
+
+    /// Creates a repeating sampler using `mode` for every filter.
+    pub fn create_sampler(&self, mode: FilterMode) -> Sampler {
+        self.sampler().filter(mode).build()
+    }
 }
 
 impl Bindable for Sampler {
@@ -45,7 +187,7 @@ impl Bindable for Sampler {
     }
 
     fn binding_type(&self) -> BindingType {
-        BindingType::Sampler(SamplerBindingType::Filtering)
+        BindingType::Sampler(self.binding_type)
     }
 }
 
@@ -0,0 +1,71 @@
+//! Textures bound for direct texel access from compute shaders.
+
+use wgpu::{
+    BindingType, StorageTextureAccess, TextureFormatFeatureFlags, TextureUsages,
+    TextureViewDimension,
+};
+
+use crate::bindings::{Bindable, BindableResourceId};
+
+use super::{format::TextureFormat, Texture};
+
+/// A [`Texture`] bound as a storage texture. Created with [`Texture::storage`].
+pub struct StorageTexture<'a, Format: TextureFormat> {
+    texture: &'a Texture<Format>,
+    access: StorageTextureAccess,
+}
+
+impl<'a, Format: TextureFormat> StorageTexture<'a, Format> {
+    pub(crate) fn new(texture: &'a Texture<Format>, access: StorageTextureAccess) -> Self {
+        let format = Format::as_format();
+        let features = texture.gpu.texture_format_features(format);
+
+        let required = match access {
+            StorageTextureAccess::ReadOnly => TextureFormatFeatureFlags::STORAGE_READ_ONLY,
+            StorageTextureAccess::WriteOnly => TextureFormatFeatureFlags::STORAGE_WRITE_ONLY,
+            StorageTextureAccess::ReadWrite => TextureFormatFeatureFlags::STORAGE_READ_WRITE,
+            StorageTextureAccess::Atomic => TextureFormatFeatureFlags::STORAGE_ATOMIC,
+        };
+
+        assert!(
+            texture
+                .texture
+                .usage()
+                .contains(TextureUsages::STORAGE_BINDING)
+                && features.flags.contains(required),
+            "{format:?} textures don't support {access:?} storage access"
+        );
+
+        assert!(
+            !matches!(
+                texture.view_dimension(),
+                TextureViewDimension::Cube | TextureViewDimension::CubeArray
+            ),
+            "Cube maps can't be bound as storage textures"
+        );
+
+        // Storage bindings can only see a single mip level, and the texture is
+        // bound through a view of all of them.
+        assert_eq!(
+            texture.mip_levels(),
+            1,
+            "Textures with mipmaps can't be bound as storage textures"
+        );
+
+        Self { texture, access }
+    }
+}
+
+impl<Format: TextureFormat> Bindable for StorageTexture<'_, Format> {
+    fn resource_id(&self) -> BindableResourceId {
+        BindableResourceId::Texture(self.texture.id)
+    }
+
+    fn binding_type(&self) -> BindingType {
+        BindingType::StorageTexture {
+            access: self.access,
+            format: Format::as_format(),
+            view_dimension: self.texture.view_dimension(),
+        }
+    }
+}
@@ -0,0 +1,492 @@
+//! Generating WGSL declarations for bound resources. Used by
+//! [`Bindings::wgsl`](super::Bindings::wgsl).
+
+use encase::{
+    internal::{CreateFrom, WriteInto},
+    ShaderType,
+};
+use nalgebra::SMatrix;
+use wgpu::{
+    BindingType, BufferBindingType, SamplerBindingType, StorageTextureAccess, TextureSampleType,
+    TextureViewDimension,
+};
+
+use super::{
+    buffer::{
+        mutability::{Immutable, Mutable},
+        BufferRole, PodStorageBuffer, StorageBuffer, TypedBuffer, UniformBuffer,
+    },
+    collection::texture_collection::TextureCollection,
+    texture::{format::TextureFormat, Sampler, StorageTexture, Texture},
+    Bindable,
+};
+
+/// A Rust type with a matching WGSL type. Implement this for your own
+/// `ShaderType` structs to use them in [`Bindings::wgsl`](super::Bindings::wgsl)
+/// declarations.
+pub trait WgslType {
+    fn wgsl_type() -> String;
+}
+
+/// A resource that knows the WGSL type it should be declared with.
+pub trait WgslBinding: Bindable {
+    fn wgsl_type(&self) -> String {
+        resource_type(&self.binding_type())
+    }
+}
+
+macro_rules! scalar_types {
+    ($($ty:ty => $name:literal),*) => {
+        $(
+            impl WgslType for $ty {
+                fn wgsl_type() -> String {
+                    $name.into()
+                }
+            }
+        )*
+    };
+}
+
+scalar_types!(f32 => "f32", u32 => "u32", i32 => "i32");
+
+impl<T: WgslType, const R: usize, const C: usize> WgslType for SMatrix<T, R, C> {
+    fn wgsl_type() -> String {
+        match C {
+            1 => format!("vec{R}<{}>", T::wgsl_type()),
+            _ => format!("mat{C}x{R}<{}>", T::wgsl_type()),
+        }
+    }
+}
+
+impl<T: WgslType, const N: usize> WgslType for [T; N] {
+    fn wgsl_type() -> String {
+        format!("array<{}, {N}>", T::wgsl_type())
+    }
+}
+
+impl<T: WgslType> WgslType for Vec<T> {
+    fn wgsl_type() -> String {
+        format!("array<{}>", T::wgsl_type())
+    }
+}
+
+/// Declares `resource` as `name` at the given group and binding.
+pub fn declaration(
+    group: u32,
+    binding: u32,
+    name: &str,
+    resource: &impl Bindable,
+    ty: &str,
+) -> String {
+    let space = match resource.binding_type() {
+        BindingType::Buffer { ty, .. } => match ty {
+            BufferBindingType::Uniform => "<uniform>",
+            BufferBindingType::Storage { read_only: true } => "<storage, read>",
+            BufferBindingType::Storage { read_only: false } => "<storage, read_write>",
+        },
+        _ => "",
+    };
+
+    let ty = match resource.count() {
+        Some(count) => format!("binding_array<{ty}, {count}>"),
+        None => ty.to_owned(),
+    };
+
+    format!("@group({group}) @binding({binding}) var{space} {name}: {ty};\n")
+}
+
+/// The WGSL type of any binding other than a buffer, whose contents can't be
+/// known from the binding type alone.
+fn resource_type(ty: &BindingType) -> String {
+    match ty {
+        BindingType::Buffer { .. } => panic!("Buffers must provide their own WGSL type"),
+        BindingType::Sampler(SamplerBindingType::Comparison) => "sampler_comparison".into(),
+        BindingType::Sampler(_) => "sampler".into(),
+        BindingType::Texture {
+            sample_type: TextureSampleType::Depth,
+            view_dimension,
+            multisampled,
+        } => {
+            let multisampled = if *multisampled { "multisampled_" } else { "" };
+            format!("texture_depth_{multisampled}{}", dimension(*view_dimension))
+        }
+        BindingType::Texture {
+            sample_type,
+            view_dimension,
+            multisampled,
+        } => {
+            let multisampled = if *multisampled { "multisampled_" } else { "" };
+            let sample_type = match sample_type {
+                TextureSampleType::Sint => "i32",
+                TextureSampleType::Uint => "u32",
+                _ => "f32",
+            };
+            format!(
+                "texture_{multisampled}{}<{sample_type}>",
+                dimension(*view_dimension)
+            )
+        }
+        BindingType::StorageTexture {
+            access,
+            format,
+            view_dimension,
+        } => {
+            let access = match access {
+                StorageTextureAccess::ReadOnly => "read",
+                StorageTextureAccess::WriteOnly => "write",
+                StorageTextureAccess::ReadWrite | StorageTextureAccess::Atomic => "read_write",
+            };
+            let format = texel_format(*format);
+            format!(
+                "texture_storage_{}<{format}, {access}>",
+                dimension(*view_dimension)
+            )
+        }
+        BindingType::AccelerationStructure => "acceleration_structure".into(),
+    }
+}
+
+/// The WGSL name of a storage texture's texel format.
+fn texel_format(format: wgpu::TextureFormat) -> &'static str {
+    use wgpu::TextureFormat as F;
+    match format {
+        F::R8Unorm => "r8unorm",
+        F::R8Snorm => "r8snorm",
+        F::R8Uint => "r8uint",
+        F::R8Sint => "r8sint",
+        F::R16Unorm => "r16unorm",
+        F::R16Snorm => "r16snorm",
+        F::R16Uint => "r16uint",
+        F::R16Sint => "r16sint",
+        F::R16Float => "r16float",
+        F::Rg8Unorm => "rg8unorm",
+        F::Rg8Snorm => "rg8snorm",
+        F::Rg8Uint => "rg8uint",
+        F::Rg8Sint => "rg8sint",
+        F::R32Uint => "r32uint",
+        F::R32Sint => "r32sint",
+        F::R32Float => "r32float",
+        F::Rg16Unorm => "rg16unorm",
+        F::Rg16Snorm => "rg16snorm",
+        F::Rg16Uint => "rg16uint",
+        F::Rg16Sint => "rg16sint",
+        F::Rg16Float => "rg16float",
+        F::Rgba8Unorm => "rgba8unorm",
+        F::Rgba8Snorm => "rgba8snorm",
+        F::Rgba8Uint => "rgba8uint",
+        F::Rgba8Sint => "rgba8sint",
+        F::Bgra8Unorm => "bgra8unorm",
+        F::Rgb10a2Uint => "rgb10a2uint",
+        F::Rgb10a2Unorm => "rgb10a2unorm",
+        F::Rg11b10Ufloat => "rg11b10float",
+        F::R64Uint => "r64uint",
+        F::Rg32Uint => "rg32uint",
+        F::Rg32Sint => "rg32sint",
+        F::Rg32Float => "rg32float",
+        F::Rgba16Unorm => "rgba16unorm",
+        F::Rgba16Snorm => "rgba16snorm",
+        F::Rgba16Uint => "rgba16uint",
+        F::Rgba16Sint => "rgba16sint",
+        F::Rgba16Float => "rgba16float",
+        F::Rgba32Uint => "rgba32uint",
+        F::Rgba32Sint => "rgba32sint",
+        F::Rgba32Float => "rgba32float",
+        _ => panic!("{format:?} has no WGSL storage texel format"),
+    }
+}
+
+fn dimension(dimension: TextureViewDimension) -> &'static str {
+    match dimension {
+        TextureViewDimension::D1 => "1d",
+        TextureViewDimension::D2 => "2d",
+        TextureViewDimension::D2Array => "2d_array",
+        TextureViewDimension::Cube => "cube",
+        TextureViewDimension::CubeArray => "cube_array",
+        TextureViewDimension::D3 => "3d",
+    }
+}
+
+impl<T: WgslBinding + ?Sized> WgslBinding for &T {
+    fn wgsl_type(&self) -> String {
+        (**self).wgsl_type()
+    }
+}
+
+impl<T: ShaderType + WriteInto + CreateFrom + WgslType> WgslBinding for StorageBuffer<T, Mutable> {
+    fn wgsl_type(&self) -> String {
+        T::wgsl_type()
+    }
+}
+
+impl<T: ShaderType + WriteInto + CreateFrom + WgslType> WgslBinding
+    for StorageBuffer<T, Immutable>
+{
+    fn wgsl_type(&self) -> String {
+        T::wgsl_type()
+    }
+}
+
+impl<T: WgslType> WgslBinding for PodStorageBuffer<T, Mutable> {
+    fn wgsl_type(&self) -> String {
+        format!("array<{}>", T::wgsl_type())
+    }
+}
+
+impl<T: WgslType> WgslBinding for PodStorageBuffer<T, Immutable> {
+    fn wgsl_type(&self) -> String {
+        format!("array<{}>", T::wgsl_type())
+    }
+}
+
+impl<T: WgslType> WgslBinding for UniformBuffer<T> {
+    fn wgsl_type(&self) -> String {
+        T::wgsl_type()
+    }
+}
+
+impl<T: WgslType> WgslBinding for TypedBuffer<T> {
+    fn wgsl_type(&self) -> String {
+        T::wgsl_type()
+    }
+}
+
+impl<T: WgslType> WgslBinding for BufferRole<'_, T> {
+    fn wgsl_type(&self) -> String {
+        T::wgsl_type()
+    }
+}
+
+impl<Format: TextureFormat> WgslBinding for Texture<Format> {}
+impl<Format: TextureFormat> WgslBinding for StorageTexture<'_, Format> {}
+impl WgslBinding for Sampler {}
+impl WgslBinding for TextureCollection {}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use wgpu::{
+        BindingType, BufferBindingType, SamplerBindingType, StorageTextureAccess, TextureFormat,
+        TextureSampleType, TextureViewDimension,
+    };
+
+    use super::{declaration, resource_type, texel_format, WgslBinding};
+    use crate::{
+        bindings::{Bindable, BindableResourceId},
+        misc::ids::BufferId,
+    };
+
+    /// A resource with a fixed binding type, standing in for real buffers,
+    /// textures, and samplers.
+    struct Resource {
+        id: BindableResourceId,
+        ty: BindingType,
+        count: Option<NonZeroU32>,
+    }
+
+    impl Resource {
+        fn new(ty: BindingType) -> Self {
+            Self {
+                id: BufferId::new().into(),
+                ty,
+                count: None,
+            }
+        }
+    }
+
+    impl Bindable for Resource {
+        fn resource_id(&self) -> BindableResourceId {
+            self.id
+        }
+
+        fn binding_type(&self) -> BindingType {
+            self.ty
+        }
+
+        fn count(&self) -> Option<NonZeroU32> {
+            self.count
+        }
+    }
+
+    impl WgslBinding for Resource {}
+
+    fn buffer(ty: BufferBindingType) -> Resource {
+        Resource::new(BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        })
+    }
+
+    fn texture(sample_type: TextureSampleType, view_dimension: TextureViewDimension) -> Resource {
+        Resource::new(BindingType::Texture {
+            sample_type,
+            view_dimension,
+            multisampled: false,
+        })
+    }
+
+    fn storage_texture(format: TextureFormat, access: StorageTextureAccess) -> Resource {
+        Resource::new(BindingType::StorageTexture {
+            access,
+            format,
+            view_dimension: TextureViewDimension::D2,
+        })
+    }
+
+    fn sampler() -> Resource {
+        Resource::new(BindingType::Sampler(SamplerBindingType::Filtering))
+    }
+
+    #[test]
+    fn buffer_declarations() {
+        let uniform = buffer(BufferBindingType::Uniform);
+        assert_eq!(
+            declaration(0, 0, "a", &uniform, "vec4<f32>"),
+            "@group(0) @binding(0) var<uniform> a: vec4<f32>;\n"
+        );
+
+        let read = buffer(BufferBindingType::Storage { read_only: true });
+        assert_eq!(
+            declaration(1, 2, "b", &read, "array<u32>"),
+            "@group(1) @binding(2) var<storage, read> b: array<u32>;\n"
+        );
+
+        let read_write = buffer(BufferBindingType::Storage { read_only: false });
+        assert_eq!(
+            declaration(0, 3, "c", &read_write, "array<f32>"),
+            "@group(0) @binding(3) var<storage, read_write> c: array<f32>;\n"
+        );
+    }
+
+    #[test]
+    fn texture_declarations() {
+        let color = texture(
+            TextureSampleType::Float { filterable: true },
+            TextureViewDimension::D2,
+        );
+        assert_eq!(
+            declaration(0, 0, "a", &color, &color.wgsl_type()),
+            "@group(0) @binding(0) var a: texture_2d<f32>;\n"
+        );
+
+        let mut array = texture(TextureSampleType::Uint, TextureViewDimension::D2Array);
+        array.count = NonZeroU32::new(4);
+        assert_eq!(
+            declaration(0, 1, "b", &array, &array.wgsl_type()),
+            "@group(0) @binding(1) var b: binding_array<texture_2d_array<u32>, 4>;\n"
+        );
+
+        let depth = texture(TextureSampleType::Depth, TextureViewDimension::Cube);
+        assert_eq!(depth.wgsl_type(), "texture_depth_cube");
+    }
+
+    #[test]
+    fn storage_texture_declarations() {
+        let write = storage_texture(TextureFormat::Rgba8Unorm, StorageTextureAccess::WriteOnly);
+        assert_eq!(
+            declaration(1, 0, "a", &write, &write.wgsl_type()),
+            "@group(1) @binding(0) var a: texture_storage_2d<rgba8unorm, write>;\n"
+        );
+
+        let read_write = storage_texture(TextureFormat::R32Float, StorageTextureAccess::ReadWrite);
+        assert_eq!(
+            read_write.wgsl_type(),
+            "texture_storage_2d<r32float, read_write>"
+        );
+    }
+
+    #[test]
+    fn sampler_declarations() {
+        let sampler = sampler();
+        assert_eq!(
+            declaration(0, 0, "a", &sampler, &sampler.wgsl_type()),
+            "@group(0) @binding(0) var a: sampler;\n"
+        );
+        assert_eq!(
+            resource_type(&BindingType::Sampler(SamplerBindingType::Comparison)),
+            "sampler_comparison"
+        );
+    }
+
+    #[test]
+    fn texel_formats() {
+        assert_eq!(texel_format(TextureFormat::Rgba16Float), "rgba16float");
+        assert_eq!(texel_format(TextureFormat::Rg11b10Ufloat), "rg11b10float");
+        assert_eq!(texel_format(TextureFormat::Bgra8Unorm), "bgra8unorm");
+    }
+
+    #[test]
+    #[should_panic(expected = "has no WGSL storage texel format")]
+    fn texel_format_unsupported() {
+        texel_format(TextureFormat::Depth32Float);
+    }
+
+    #[test]
+    #[should_panic(expected = "Buffers must provide their own WGSL type")]
+    fn buffer_resource_type() {
+        buffer(BufferBindingType::Uniform).wgsl_type();
+    }
+
+    #[cfg(feature = "derive")]
+    mod derive {
+        use super::{buffer, sampler, storage_texture, texture, Resource};
+        use crate::bindings::{Bindable, Bindings};
+        use wgpu::{
+            BufferBindingType, StorageTextureAccess, TextureFormat, TextureSampleType,
+            TextureViewDimension,
+        };
+
+        #[derive(crate::Bindings)]
+        struct Group<'a> {
+            #[binding(2)]
+            output: &'a Resource,
+            #[binding(0, ty = "array<f32>")]
+            input: Resource,
+            color: Resource,
+            #[binding(4)]
+            sampler: Resource,
+            #[binding(3)]
+            params: Resource,
+        }
+
+        #[test]
+        fn binding_indices() {
+            let output =
+                storage_texture(TextureFormat::Rgba8Unorm, StorageTextureAccess::WriteOnly);
+            let group = Group {
+                output: &output,
+                input: buffer(BufferBindingType::Storage { read_only: true }),
+                color: texture(
+                    TextureSampleType::Float { filterable: true },
+                    TextureViewDimension::D2,
+                ),
+                sampler: sampler(),
+                params: texture(TextureSampleType::Sint, TextureViewDimension::D3),
+            };
+
+            let ids = group
+                .bindings()
+                .iter()
+                .map(|x| x.resource_id())
+                .collect::<Vec<_>>();
+            let expected = [
+                &group.input,
+                &group.color,
+                &output,
+                &group.params,
+                &group.sampler,
+            ];
+            assert_eq!(ids, expected.map(|x| x.resource_id()));
+
+            assert_eq!(
+                group.wgsl(),
+                "@group(0) @binding(0) var<storage, read> input: array<f32>;\n\
+                 @group(0) @binding(1) var color: texture_2d<f32>;\n\
+                 @group(0) @binding(2) var output: texture_storage_2d<rgba8unorm, write>;\n\
+                 @group(0) @binding(3) var params: texture_3d<i32>;\n\
+                 @group(0) @binding(4) var sampler: sampler;\n"
+            );
+        }
+    }
+}
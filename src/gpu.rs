@@ -5,9 +5,9 @@ use std::{mem, ops::Deref, sync::Arc};
 use anyhow::{Context, Result};
 use parking_lot::Mutex;
 use wgpu::{
-    AdapterInfo, CommandBuffer, CommandEncoder, CommandEncoderDescriptor, Device, DeviceDescriptor,
-    Features, Instance, InstanceDescriptor, Limits, MaintainBase, PowerPreference, Queue,
-    RequestAdapterOptions,
+    Adapter, AdapterInfo, Buffer, CommandBuffer, CommandEncoder, CommandEncoderDescriptor, Device,
+    DeviceDescriptor, DownlevelFlags, Features, Instance, InstanceDescriptor, Limits, MaintainBase,
+    PowerPreference, Queue, RequestAdapterOptions, TextureFormat, TextureFormatFeatures,
 };
 
 use crate::{
@@ -15,7 +15,12 @@ use crate::{
         buffer::{IndexBuffer, VertexBuffer},
         manager::BindingManager,
     },
-    misc::default_buffer::DefaultBuffers,
+    misc::{
+        default_buffer::DefaultBuffers,
+        scratch::ScratchBuffers,
+        shader_cache::ShaderCache,
+        staging::{StagingPool, UploadBelt},
+    },
     pipeline::render::Vertex,
 };
 
@@ -27,12 +32,17 @@ pub struct Gpu {
 pub struct GpuInner {
     #[cfg(feature = "interactive")]
     pub(crate) instance: Instance,
+    pub(crate) adapter: Adapter,
     pub(crate) device: Device,
     pub(crate) queue: Queue,
     pub(crate) info: AdapterInfo,
 
     pub(crate) binding_manager: BindingManager,
     default_buffers: DefaultBuffers,
+    pub(crate) shader_cache: ShaderCache,
+    pub(crate) scratch: ScratchBuffers,
+    pub(crate) staging: StagingPool,
+    upload_belt: Option<UploadBelt>,
     dispatch_queue: Mutex<DispatchQueue>,
 }
 
@@ -40,6 +50,7 @@ pub struct GpuBuilder {
     limits: Limits,
     features: Features,
     power_preference: PowerPreference,
+    staging_belt: Option<u64>,
 }
 
 #[derive(Default)]
@@ -67,6 +78,16 @@ impl GpuBuilder {
         }
     }
 
+    /// Copies uploads of at least `threshold` bytes through a
+    /// [`wgpu::util::StagingBelt`] rather than letting wgpu allocate a new
+    /// staging buffer for each one.
+    pub fn with_staging_belt(self, threshold: u64) -> Self {
+        Self {
+            staging_belt: Some(threshold),
+            ..self
+        }
+    }
+
     pub fn with_raytracing(self) -> Self {
         self.with_features(
             Features::EXPERIMENTAL_RAY_TRACING_ACCELERATION_STRUCTURE
@@ -96,12 +117,17 @@ impl GpuBuilder {
             inner: Arc::new(GpuInner {
                 #[cfg(feature = "interactive")]
                 instance,
+                adapter,
                 device,
                 queue,
                 info,
 
                 binding_manager: BindingManager::new(),
                 default_buffers: DefaultBuffers::empty(),
+                shader_cache: ShaderCache::new(),
+                scratch: ScratchBuffers::empty(),
+                staging: StagingPool::empty(),
+                upload_belt: self.staging_belt.map(UploadBelt::new),
                 dispatch_queue: Mutex::new(DispatchQueue::default()),
             }),
         })
@@ -114,6 +140,7 @@ impl Gpu {
             limits: Limits::default(),
             features: Features::VERTEX_WRITABLE_STORAGE,
             power_preference: PowerPreference::None,
+            staging_belt: None,
         }
     }
 
@@ -126,6 +153,23 @@ impl Gpu {
         &self.info
     }
 
+    /// Returns the usages and features the texture format supports on this device.
+    pub fn texture_format_features(&self, format: TextureFormat) -> TextureFormatFeatures {
+        // Downlevel devices are validated against the adapter's own support,
+        // as they may not meet WebGPU's guarantees.
+        let features = self.device.features();
+        let downlevel = !self
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::WEBGPU_TEXTURE_FORMAT_SUPPORT);
+        if features.contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) || downlevel {
+            self.adapter.get_texture_format_features(format)
+        } else {
+            format.guaranteed_format_features(features)
+        }
+    }
+
     /// Processes any resource cleanups and mapping callbacks
     pub fn poll(&self) {
         self.device.poll(MaintainBase::Poll);
@@ -138,6 +182,17 @@ impl Gpu {
 }
 
 impl Gpu {
+    /// Writes `data` into the buffer at `offset`, going through the staging
+    /// belt if one is enabled and the write is large enough.
+    pub(crate) fn write_buffer(&self, buffer: &Buffer, offset: u64, data: &[u8]) {
+        match &self.upload_belt {
+            Some(belt) if belt.accepts(offset, data.len() as u64) => {
+                belt.write(&self.device, &self.queue, buffer, offset, data)
+            }
+            _ => self.queue.write_buffer(buffer, offset, data),
+        }
+    }
+
     pub(crate) fn default_buffers(&self) -> &(VertexBuffer<Vertex>, IndexBuffer) {
         self.default_buffers.get(self)
     }
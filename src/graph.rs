@@ -0,0 +1,389 @@
+//! Task graphs for submitting many dependent operations at once.
+//!
+//! Each node in the graph is a compute dispatch, buffer copy, or render pass
+//! along with the set of resources it reads from and writes to. A node depends
+//! on an earlier node when it reads something that node writes, or writes
+//! something that node reads or writes. The whole graph is recorded into a
+//! single command buffer and submitted together.
+//!
+//! Nodes always run in the order they were added. A node can only depend on
+//! nodes added before it, so that order respects every dependency. The graph
+//! doesn't use the dependencies itself; they are only for inspecting it with
+//! [`TaskGraph::dependencies`] and [`TaskGraph::dot`].
+//!
+//! ```rust,ignore
+//! let mut graph = gpu.task_graph();
+//! graph.dispatch("advect", &mut advect, workgroups);
+//! graph.dispatch("project", &mut project, workgroups);
+//! graph.copy("snapshot", &velocity, 0..size, &history, 0);
+//!
+//! println!("{}", graph.dot());
+//! graph.submit();
+//! ```
+
+use std::{collections::HashMap, fmt::Write, ops::Range};
+
+use nalgebra::Vector3;
+use wgpu::{BindGroup, Buffer, CommandEncoder, RenderPass, TextureView};
+
+use crate::{
+    bindings::{
+        buffer::BufferBinding,
+        texture::{format::TextureFormat, Texture},
+        Bindable, BindableResourceId,
+    },
+    gpu::Gpu,
+    pipeline::{
+        compute::{record_dispatch, ComputePipeline},
+        is_writable,
+        render::pass::{attachments, begin_render_pass},
+    },
+};
+
+pub struct TaskGraph<'a> {
+    gpu: Gpu,
+    nodes: Vec<Node<'a>>,
+}
+
+/// A node that was just added to a [`TaskGraph`], used to declare any extra
+/// resources it uses.
+pub struct NodeBuilder<'g, 'a> {
+    gpu: &'g Gpu,
+    node: &'g mut Node<'a>,
+}
+
+struct Node<'a> {
+    name: String,
+    task: Task<'a>,
+
+    reads: Vec<BindableResourceId>,
+    writes: Vec<BindableResourceId>,
+}
+
+enum Task<'a> {
+    Dispatch {
+        pipeline: wgpu::ComputePipeline,
+        bind_group: BindGroup,
+        workgroups: Vector3<u32>,
+    },
+    Copy {
+        source: Buffer,
+        range: Range<u64>,
+        destination: Buffer,
+        offset: u64,
+    },
+    Render {
+        view: TextureView,
+        depth: TextureView,
+        callback: Box<dyn FnOnce(&mut RenderPass) + 'a>,
+    },
+}
+
+impl<'a> TaskGraph<'a> {
+    /// Adds a dispatch of the pipeline. The resources it reads and writes are
+    /// taken from its bindings.
+    pub fn dispatch(
+        &mut self,
+        name: impl Into<String>,
+        pipeline: &mut ComputePipeline,
+        workgroups: Vector3<u32>,
+    ) -> NodeBuilder<'_, 'a> {
+        pipeline.recreate_bind_group();
+
+        let mut reads = Vec::new();
+        let mut writes = Vec::new();
+        for (id, entry) in pipeline.entries.iter().zip(&pipeline.bind_group_layout) {
+            let id = self.gpu.binding_manager.resolve(*id);
+            if is_writable(&entry.ty) {
+                writes.push(id);
+            } else {
+                reads.push(id);
+            }
+        }
+
+        self.push(Node {
+            name: name.into(),
+            task: Task::Dispatch {
+                pipeline: pipeline.pipeline.clone(),
+                bind_group: pipeline.bind_group.clone(),
+                workgroups,
+            },
+            reads,
+            writes,
+        })
+    }
+
+    /// Adds a copy of the bytes in `range` of `source` into `destination`,
+    /// starting at `offset` bytes in. Panics on the same invalid copies as
+    /// [`Gpu::copy_buffer`].
+    pub fn copy(
+        &mut self,
+        name: impl Into<String>,
+        source: &impl BufferBinding,
+        range: Range<u64>,
+        destination: &impl BufferBinding,
+        offset: u64,
+    ) -> NodeBuilder<'_, 'a> {
+        let (source_buffer, destination_buffer) =
+            self.gpu.checked_copy(source, &range, destination, offset);
+        let task = Task::Copy {
+            source: source_buffer,
+            range,
+            destination: destination_buffer,
+            offset,
+        };
+
+        self.push(Node {
+            name: name.into(),
+            task,
+            reads: vec![source.get_id().into()],
+            writes: vec![destination.get_id().into()],
+        })
+    }
+
+    /// Adds a render pass into the textures, like [`Gpu::render_pass`].
+    /// Resources used by the pipelines drawn in the callback need to be
+    /// declared with [`NodeBuilder::reads`] and [`NodeBuilder::writes`].
+    pub fn render<Color: TextureFormat, DepthFormat: TextureFormat>(
+        &mut self,
+        name: impl Into<String>,
+        texture: &Texture<Color>,
+        depth: &Texture<DepthFormat>,
+        callback: impl FnOnce(&mut RenderPass) + 'a,
+    ) -> NodeBuilder<'_, 'a> {
+        let (view, depth_view) = attachments(texture, depth);
+        let task = Task::Render {
+            view,
+            depth: depth_view,
+            callback: Box::new(callback),
+        };
+
+        self.push(Node {
+            name: name.into(),
+            task,
+            reads: Vec::new(),
+            writes: vec![texture.id.into(), depth.id.into()],
+        })
+    }
+
+    fn push(&mut self, node: Node<'a>) -> NodeBuilder<'_, 'a> {
+        self.nodes.push(node);
+        NodeBuilder {
+            gpu: &self.gpu,
+            node: self.nodes.last_mut().unwrap(),
+        }
+    }
+
+    /// Returns every `(dependency, dependent)` pair of node indices. Nodes are
+    /// numbered in the order they were added, and a dependency always comes
+    /// before its dependent.
+    pub fn dependencies(&self) -> Vec<(usize, usize)> {
+        dependencies(self.nodes.iter().map(|node| (&node.reads, &node.writes)))
+    }
+
+    /// Renders the graph in the [DOT language](https://graphviz.org/doc/info/lang.html)
+    /// for debugging with Graphviz.
+    pub fn dot(&self) -> String {
+        let nodes = self.nodes.iter().map(|node| {
+            let kind = match node.task {
+                Task::Dispatch { .. } => "dispatch",
+                Task::Copy { .. } => "copy",
+                Task::Render { .. } => "render",
+            };
+            (node.name.as_str(), kind)
+        });
+
+        dot(nodes, &self.dependencies())
+    }
+
+    /// Records every node, in the order they were added, into a single command
+    /// buffer and submits it, along with anything already in the dispatch
+    /// queue.
+    pub fn submit(self) {
+        self.submit_inner(true);
+    }
+
+    /// Records every node into a single command buffer and adds it to the
+    /// dispatch queue, to be submitted with the next compute dispach, render
+    /// pass, or call to [`Gpu::flush_dispatch_queue`].
+    pub fn queue(self) {
+        self.submit_inner(false);
+    }
+
+    fn submit_inner(self, immediate: bool) {
+        let Self { gpu, nodes } = self;
+        gpu.dispach(
+            |encoder| nodes.into_iter().for_each(|node| node.task.record(encoder)),
+            immediate,
+        );
+    }
+}
+
+impl NodeBuilder<'_, '_> {
+    /// Declares that the node reads from the resource.
+    pub fn reads(self, resource: &impl Bindable) -> Self {
+        let id = self.gpu.binding_manager.resolve(resource.resource_id());
+        self.node.reads.push(id);
+        self
+    }
+
+    /// Declares that the node writes to the resource.
+    pub fn writes(self, resource: &impl Bindable) -> Self {
+        let id = self.gpu.binding_manager.resolve(resource.resource_id());
+        self.node.writes.push(id);
+        self
+    }
+}
+
+impl Task<'_> {
+    fn record(self, encoder: &mut CommandEncoder) {
+        match self {
+            Task::Dispatch {
+                pipeline,
+                bind_group,
+                workgroups,
+            } => record_dispatch(encoder, &pipeline, &bind_group, workgroups),
+            Task::Copy {
+                source,
+                range,
+                destination,
+                offset,
+            } => encoder.copy_buffer_to_buffer(
+                &source,
+                range.start,
+                &destination,
+                offset,
+                range.end - range.start,
+            ),
+            Task::Render {
+                view,
+                depth,
+                callback,
+            } => callback(&mut begin_render_pass(encoder, &view, &depth)),
+        }
+    }
+}
+
+/// Works out the dependencies between nodes given as `(reads, writes)` pairs.
+fn dependencies<'n>(
+    nodes: impl Iterator<Item = (&'n Vec<BindableResourceId>, &'n Vec<BindableResourceId>)>,
+) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+    let mut last_writer = HashMap::new();
+    let mut readers = HashMap::<_, Vec<usize>>::new();
+
+    for (index, (reads, writes)) in nodes.enumerate() {
+        for id in reads {
+            edges.extend(last_writer.get(id).map(|&writer| (writer, index)));
+        }
+
+        for id in writes {
+            edges.extend(last_writer.get(id).map(|&writer| (writer, index)));
+            let readers = readers.remove(id).unwrap_or_default();
+            edges.extend(readers.into_iter().map(|reader| (reader, index)));
+        }
+
+        for id in reads {
+            readers.entry(*id).or_default().push(index);
+        }
+
+        for id in writes {
+            last_writer.insert(*id, index);
+        }
+    }
+
+    edges.retain(|(from, to)| from != to);
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+/// Renders nodes given as `(name, kind)` pairs and the edges between them.
+fn dot<'n>(nodes: impl Iterator<Item = (&'n str, &'n str)>, edges: &[(usize, usize)]) -> String {
+    let mut out = String::from("digraph {\n    node [shape=box];\n");
+
+    for (index, (name, kind)) in nodes.enumerate() {
+        let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(out, "    {index} [label=\"{name}\\n({kind})\"];").unwrap();
+    }
+
+    for (from, to) in edges {
+        writeln!(out, "    {from} -> {to};").unwrap();
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+impl Gpu {
+    /// Creates an empty task graph.
+    pub fn task_graph<'a>(&self) -> TaskGraph<'a> {
+        TaskGraph {
+            gpu: self.clone(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bindings::BindableResourceId, misc::ids::BufferId};
+
+    fn buffer() -> BindableResourceId {
+        BufferId::new().into()
+    }
+
+    fn dependencies(
+        nodes: &[(Vec<BindableResourceId>, Vec<BindableResourceId>)],
+    ) -> Vec<(usize, usize)> {
+        super::dependencies(nodes.iter().map(|(reads, writes)| (reads, writes)))
+    }
+
+    #[test]
+    fn read_after_write() {
+        let (a, b) = (buffer(), buffer());
+        let nodes = [
+            (vec![], vec![a]),
+            (vec![a], vec![b]),
+            (vec![b], vec![]),
+            (vec![a], vec![]),
+        ];
+        assert_eq!(dependencies(&nodes), [(0, 1), (0, 3), (1, 2)]);
+    }
+
+    #[test]
+    fn write_after_read_and_write() {
+        let a = buffer();
+        let nodes = [
+            (vec![], vec![a]),
+            (vec![a], vec![]),
+            (vec![a], vec![]),
+            (vec![], vec![a]),
+            (vec![], vec![a]),
+        ];
+        assert_eq!(
+            dependencies(&nodes),
+            [(0, 1), (0, 2), (0, 3), (1, 3), (2, 3), (3, 4)]
+        );
+    }
+
+    #[test]
+    fn independent_and_self_edges() {
+        let (a, b) = (buffer(), buffer());
+        let nodes = [
+            (vec![a], vec![a]),
+            (vec![b], vec![b]),
+            (vec![a, a], vec![a]),
+        ];
+        assert_eq!(dependencies(&nodes), [(0, 2)]);
+    }
+
+    #[test]
+    fn dot() {
+        let nodes = [("advect", "dispatch"), ("say \"hi\"", "copy")];
+        assert_eq!(
+            super::dot(nodes.into_iter(), &[(0, 1)]),
+            "digraph {\n    node [shape=box];\n    0 [label=\"advect\\n(dispatch)\"];\n    1 [label=\"say \\\"hi\\\"\\n(copy)\"];\n    0 -> 1;\n}\n"
+        );
+    }
+}
@@ -2,16 +2,28 @@
 
 use wgpu::TextureFormat;
 
+pub mod algorithms;
 pub mod bindings;
 pub mod gpu;
+pub mod graph;
 #[cfg(feature = "interactive")]
 pub mod interactive;
 pub mod misc;
 pub mod pipeline;
+pub mod snapshot;
+
+#[cfg(feature = "derive")]
+pub use tufa_derive::{Bindings, Vertex};
+
+// Lets the derive macros' `::tufa` paths resolve in this crate's own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as tufa;
 
 pub mod export {
     //! Exported types from crates tufa uses internally.
 
+    #[cfg(feature = "image")]
+    pub use image;
     #[cfg(feature = "interactive")]
     pub use {egui, winit};
     pub use {encase, nalgebra, wgpu};
@@ -24,9 +36,9 @@ pub mod prelude {
 
     pub use crate::{
         bindings::{buffer::*, texture::*},
-        export::wgpu::{include_wgsl, RenderPass, ShaderStages},
+        export::wgpu::{include_wgsl, RenderPass, ShaderStages, StorageTextureAccess},
         gpu::Gpu,
-        pipeline::render::{RenderPipeline, Vertex},
+        pipeline::render::{layout::VertexLayout, RenderPipeline, Vertex},
     };
 
     #[cfg(feature = "interactive")]
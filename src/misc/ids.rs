@@ -37,7 +37,8 @@ id_types! {
     PipelineId,
     AccelerationStructureId,
 
-    TextureCollectionId
+    TextureCollectionId,
+    PingPongId
 }
 
 into_bindable_resource! {
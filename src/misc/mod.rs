@@ -3,4 +3,6 @@
 pub mod camera;
 pub(crate) mod default_buffer;
 pub(crate) mod ids;
-pub(crate) mod thread_ptr;
+pub(crate) mod scratch;
+pub(crate) mod shader_cache;
+pub(crate) mod staging;
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+
+use crate::{
+    bindings::buffer::{mutability::Mutable, StorageBuffer},
+    gpu::Gpu,
+    misc::ids::BufferId,
+};
+
+/// A buffer's name and index, for kernels that need several of the same kind.
+type ScratchKey = (&'static str, u32);
+
+/// Temporary storage buffers used by tufa's built-in kernels. Buffers are kept
+/// around between uses and only reallocated when a larger one is needed.
+pub(crate) struct ScratchBuffers {
+    buffers: Mutex<HashMap<ScratchKey, StorageBuffer<Vec<u32>, Mutable>>>,
+}
+
+impl ScratchBuffers {
+    pub fn empty() -> Self {
+        Self {
+            buffers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gets the scratch buffer with the given name and index, making sure it
+    /// is at least `size` bytes.
+    pub fn get(&self, gpu: &Gpu, name: &'static str, index: u32, size: u64) -> BufferId {
+        let mut buffers = self.buffers.lock();
+        if let Some(buffer) = buffers.get(&(name, index)) {
+            if buffer.get().size() >= size {
+                return buffer.buffer;
+            }
+        }
+
+        let buffer = gpu.create_storage_empty(size.max(4));
+        let id = buffer.buffer;
+        buffers.insert((name, index), buffer);
+        id
+    }
+}
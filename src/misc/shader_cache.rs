@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+use wgpu::{
+    BindGroupLayoutEntry, ComputePipeline, Device, RenderPipeline, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, TextureFormat,
+};
+
+/// Caches the shader modules and pipelines used by tufa's built-in kernels,
+/// keyed by their final (templated) source.
+pub(crate) struct ShaderCache {
+    modules: Mutex<HashMap<String, ShaderModule>>,
+    pipelines: Mutex<HashMap<(String, Vec<BindGroupLayoutEntry>), ComputePipeline>>,
+    render_pipelines: Mutex<HashMap<(String, TextureFormat), RenderPipeline>>,
+}
+
+impl ShaderCache {
+    pub fn new() -> Self {
+        Self {
+            modules: Mutex::new(HashMap::new()),
+            pipelines: Mutex::new(HashMap::new()),
+            render_pipelines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the compiled module for already templated source.
+    pub fn module(&self, device: &Device, source: &str) -> ShaderModule {
+        let mut modules = self.modules.lock();
+        if let Some(module) = modules.get(source) {
+            return module.clone();
+        }
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(source.into()),
+        });
+        modules.insert(source.to_owned(), module.clone());
+        module
+    }
+
+    /// Returns the pipeline for the templated source with the bind group
+    /// layout, calling `create` to make it the first time.
+    pub fn pipeline(
+        &self,
+        source: &str,
+        layout: &[BindGroupLayoutEntry],
+        create: impl FnOnce() -> ComputePipeline,
+    ) -> ComputePipeline {
+        let key = (source.to_owned(), layout.to_vec());
+        let mut pipelines = self.pipelines.lock();
+        pipelines.entry(key).or_insert_with(create).clone()
+    }
+
+    /// Returns the render pipeline for the source drawing into `format`,
+    /// calling `create` to make it the first time.
+    pub fn render_pipeline(
+        &self,
+        source: &str,
+        format: TextureFormat,
+        create: impl FnOnce() -> RenderPipeline,
+    ) -> RenderPipeline {
+        let key = (source.to_owned(), format);
+        let mut pipelines = self.render_pipelines.lock();
+        pipelines.entry(key).or_insert_with(create).clone()
+    }
+}
+
+/// Replaces each `(key, value)` pair in the source.
+pub(crate) fn expand(source: &str, defines: &[(&str, &str)]) -> String {
+    defines
+        .iter()
+        .fold(source.to_owned(), |source, (key, value)| {
+            source.replace(key, value)
+        })
+}
@@ -0,0 +1,127 @@
+use std::{collections::HashMap, num::NonZeroU64, sync::Arc};
+
+use parking_lot::Mutex;
+use wgpu::{
+    util::StagingBelt, Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device,
+    Queue, COPY_BUFFER_ALIGNMENT,
+};
+
+/// The smallest staging buffer handed out by the pool.
+const MIN_SIZE: u64 = 256;
+
+/// How many idle buffers are kept around for each size class.
+const MAX_IDLE: usize = 4;
+
+/// The most memory idle buffers can take up in total.
+const MAX_IDLE_BYTES: u64 = 64 << 20;
+
+/// Mappable buffers used for downloads. Buffers are bucketed by size, rounded
+/// up to a power of two, and returned to the pool once they have been read.
+#[derive(Clone)]
+pub(crate) struct StagingPool {
+    free: Arc<Mutex<FreeBuffers>>,
+}
+
+#[derive(Default)]
+struct FreeBuffers {
+    classes: HashMap<u64, Vec<Buffer>>,
+    bytes: u64,
+}
+
+/// Uploads at least `threshold` bytes large are copied through a staging belt
+/// instead of [`Queue::write_buffer`].
+pub(crate) struct UploadBelt {
+    threshold: u64,
+    belt: Mutex<StagingBelt>,
+}
+
+impl StagingPool {
+    pub fn empty() -> Self {
+        Self {
+            free: Arc::new(Mutex::new(FreeBuffers::default())),
+        }
+    }
+
+    /// Gets an unmapped `MAP_READ` buffer that is at least `size` bytes. If
+    /// rounding up to a size class would exceed the device's buffer size
+    /// limit, a buffer of exactly `size` bytes is created instead, which won't
+    /// be pooled.
+    pub fn take(&self, device: &Device, size: u64) -> Buffer {
+        let class = size.next_power_of_two().max(MIN_SIZE);
+        if class > device.limits().max_buffer_size {
+            return create_staging(device, size.next_multiple_of(COPY_BUFFER_ALIGNMENT));
+        }
+
+        let mut free = self.free.lock();
+        if let Some(buffer) = free.classes.get_mut(&class).and_then(Vec::pop) {
+            free.bytes -= class;
+            return buffer;
+        }
+
+        drop(free);
+        create_staging(device, class)
+    }
+
+    /// Unmaps the buffer and returns it to the pool, unless the pool already
+    /// holds enough idle buffers.
+    pub fn give(&self, buffer: Buffer) {
+        buffer.unmap();
+
+        let size = buffer.size();
+        if !size.is_power_of_two() {
+            return;
+        }
+
+        let mut free = self.free.lock();
+        if free.bytes + size > MAX_IDLE_BYTES {
+            return;
+        }
+
+        let class = free.classes.entry(size).or_default();
+        if class.len() < MAX_IDLE {
+            class.push(buffer);
+            free.bytes += size;
+        }
+    }
+}
+
+fn create_staging(device: &Device, size: u64) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("tufa staging"),
+        size,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    })
+}
+
+impl UploadBelt {
+    pub fn new(threshold: u64) -> Self {
+        let threshold = threshold.max(COPY_BUFFER_ALIGNMENT);
+        Self {
+            threshold,
+            belt: Mutex::new(StagingBelt::new(threshold)),
+        }
+    }
+
+    /// If a write of `size` bytes at `offset` should go through the belt.
+    pub fn accepts(&self, offset: u64, size: u64) -> bool {
+        size >= self.threshold
+            && size.is_multiple_of(COPY_BUFFER_ALIGNMENT)
+            && offset.is_multiple_of(COPY_BUFFER_ALIGNMENT)
+    }
+
+    /// Writes `data` into the buffer at `offset`. Like [`Queue::write_buffer`],
+    /// the write takes effect before any work submitted afterwards.
+    pub fn write(&self, device: &Device, queue: &Queue, buffer: &Buffer, offset: u64, data: &[u8]) {
+        let size = NonZeroU64::new(data.len() as u64).unwrap();
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+
+        let mut belt = self.belt.lock();
+        belt.write_buffer(&mut encoder, buffer, offset, size, device)
+            .copy_from_slice(data);
+        belt.finish();
+
+        queue.submit([encoder.finish()]);
+        belt.recall();
+    }
+}
@@ -1,14 +1,16 @@
+use std::collections::HashMap;
+
 use nalgebra::Vector3;
 use wgpu::{
-    BindGroup, BindGroupLayoutDescriptor, BindGroupLayoutEntry, ComputePassDescriptor,
-    ComputePipelineDescriptor, PipelineCompilationOptions, PipelineLayoutDescriptor, ShaderModule,
-    ShaderModuleDescriptor, ShaderStages,
+    BindGroup, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BufferUsages, CommandEncoder,
+    ComputePassDescriptor, ComputePipelineDescriptor, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderStages,
 };
 
 use crate::{
-    bindings::{Bindable, BindableResourceId},
+    bindings::{buffer::BufferBinding, Bindable, BindableResourceId, Bindings},
     gpu::Gpu,
-    misc::ids::PipelineId,
+    misc::{ids::PipelineId, shader_cache},
 };
 
 use super::PipelineStatus;
@@ -17,15 +19,19 @@ pub struct ComputePipeline {
     gpu: Gpu,
 
     id: PipelineId,
-    pipeline: wgpu::ComputePipeline,
-    entries: Vec<BindableResourceId>,
-    bind_group: BindGroup,
+    pub(crate) pipeline: wgpu::ComputePipeline,
+    pub(crate) entries: Vec<BindableResourceId>,
+    pub(crate) bind_group_layout: Vec<BindGroupLayoutEntry>,
+    pub(crate) bind_group: BindGroup,
+    bind_groups: HashMap<Vec<bool>, BindGroup>,
 }
 
 pub struct ComputePipelineBuilder {
     gpu: Gpu,
 
     module: ShaderModule,
+    /// The templated source of a built-in kernel, whose pipeline is cached.
+    kernel: Option<String>,
     bind_group_layout: Vec<BindGroupLayoutEntry>,
     entries: Vec<BindableResourceId>,
 }
@@ -57,25 +63,69 @@ impl ComputePipeline {
         self.dispatch_callback_inner(workgroups, callback, false);
     }
 
-    fn recreate_bind_group(&mut self) {
-        if self.gpu.binding_manager.get_pipeline(self.id).dirty {
-            self.bind_group = self.gpu.binding_manager.create_bind_group(
-                &self.gpu.device,
-                &self.pipeline.get_bind_group_layout(0),
-                &self.entries,
-            )
-        }
+    /// Dispatches the pipeline with the workgroup counts stored in `arguments`
+    /// at `offset`, as three consecutive `u32`s. The buffer must have been
+    /// built with [`BufferBuilder::indirect`](crate::bindings::buffer::BufferBuilder::indirect),
+    /// and `offset` must be a multiple of four.
+    pub fn dispatch_indirect(&mut self, arguments: &impl BufferBinding, offset: u64) {
+        self.dispatch_indirect_inner(arguments, offset, true);
+    }
+
+    /// Queues a [`ComputePipeline::dispatch_indirect`] with the next compute
+    /// dispach, render pass, or call to [`Gpu::flush_dispatch_queue`].
+    pub fn queue_dispatch_indirect(&mut self, arguments: &impl BufferBinding, offset: u64) {
+        self.dispatch_indirect_inner(arguments, offset, false);
+    }
+
+    pub(crate) fn recreate_bind_group(&mut self) {
+        self.bind_group = self.gpu.binding_manager.bind_group(
+            &self.gpu.device,
+            &self.pipeline.get_bind_group_layout(0),
+            self.id,
+            &self.entries,
+            &mut self.bind_groups,
+        );
     }
 
     fn dispatch_inner(&mut self, workgroups: Vector3<u32>, immediate: bool) {
         self.recreate_bind_group();
+        self.gpu.dispach(
+            |encoder| record_dispatch(encoder, &self.pipeline, &self.bind_group, workgroups),
+            immediate,
+        );
+    }
+
+    fn dispatch_indirect_inner(
+        &mut self,
+        arguments: &impl BufferBinding,
+        offset: u64,
+        immediate: bool,
+    ) {
+        self.recreate_bind_group();
+
+        let arguments = self.gpu.binding_manager.get_resource(arguments.get_id());
+        let arguments = arguments.expect_buffer();
+        assert!(
+            arguments.usage().contains(BufferUsages::INDIRECT),
+            "Buffer must be built with `indirect` to be used for indirect dispatches"
+        );
+        assert!(
+            offset.is_multiple_of(4),
+            "Indirect dispatch offset {offset} must be a multiple of 4"
+        );
+        assert!(
+            offset + 12 <= arguments.size(),
+            "Indirect dispatch arguments at offset {offset} are out of bounds for a buffer of {} bytes",
+            arguments.size()
+        );
+
         self.gpu.dispach(
             |encoder| {
                 let mut compute_pass =
                     encoder.begin_compute_pass(&ComputePassDescriptor::default());
                 compute_pass.set_pipeline(&self.pipeline);
                 compute_pass.set_bind_group(0, Some(&self.bind_group), &[]);
-                compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+                compute_pass.dispatch_workgroups_indirect(arguments, offset);
             },
             immediate,
         );
@@ -89,13 +139,7 @@ impl ComputePipeline {
     ) {
         self.recreate_bind_group();
         self.gpu.dispach_callback(
-            |encoder| {
-                let mut compute_pass =
-                    encoder.begin_compute_pass(&ComputePassDescriptor::default());
-                compute_pass.set_pipeline(&self.pipeline);
-                compute_pass.set_bind_group(0, Some(&self.bind_group), &[]);
-                compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
-            },
+            |encoder| record_dispatch(encoder, &self.pipeline, &self.bind_group, workgroups),
             callback,
             immediate,
         );
@@ -116,29 +160,24 @@ impl ComputePipelineBuilder {
         self
     }
 
-    /// Converts the pipeline builder into an actual compte pipeline
-    pub fn finish(self) -> ComputePipeline {
-        let device = &self.gpu.device;
-        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: None,
-            entries: &self.bind_group_layout,
-        });
+    /// Binds every resource in the group, in order, after any already bound.
+    pub fn bind_all(mut self, bindings: &impl Bindings) -> Self {
+        for entry in bindings.bindings() {
+            self = self.bind(&entry);
+        }
 
-        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
+        self
+    }
 
-        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-            label: None,
-            layout: Some(&layout),
-            module: &self.module,
-            entry_point: Some("main"),
-            // todo: pass in constants?
-            compilation_options: PipelineCompilationOptions::default(),
-            cache: None,
-        });
+    /// Converts the pipeline builder into an actual compte pipeline
+    pub fn finish(self) -> ComputePipeline {
+        let pipeline = match &self.kernel {
+            Some(source) => {
+                (self.gpu.shader_cache)
+                    .pipeline(source, &self.bind_group_layout, || self.create_pipeline())
+            }
+            None => self.create_pipeline(),
+        };
 
         let id = PipelineId::new();
         self.gpu.binding_manager.add_pipeline(
@@ -149,18 +188,60 @@ impl ComputePipelineBuilder {
             },
         );
 
+        let mut bind_groups = HashMap::new();
         ComputePipeline {
             id,
-            bind_group: self.gpu.binding_manager.create_bind_group(
+            bind_group: self.gpu.binding_manager.bind_group(
                 &self.gpu.device,
                 &pipeline.get_bind_group_layout(0),
+                id,
                 &self.entries,
+                &mut bind_groups,
             ),
+            bind_groups,
             gpu: self.gpu,
             entries: self.entries,
+            bind_group_layout: self.bind_group_layout,
             pipeline,
         }
     }
+
+    fn create_pipeline(&self) -> wgpu::ComputePipeline {
+        let device = &self.gpu.device;
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &self.bind_group_layout,
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&layout),
+            module: &self.module,
+            entry_point: Some("main"),
+            // todo: pass in constants?
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        })
+    }
+}
+
+/// Records a compute pass that dispatches the pipeline into the encoder.
+pub(crate) fn record_dispatch(
+    encoder: &mut CommandEncoder,
+    pipeline: &wgpu::ComputePipeline,
+    bind_group: &BindGroup,
+    workgroups: Vector3<u32>,
+) {
+    let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+    compute_pass.set_pipeline(pipeline);
+    compute_pass.set_bind_group(0, Some(bind_group), &[]);
+    compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
 }
 
 impl Gpu {
@@ -173,6 +254,22 @@ impl Gpu {
             gpu: self.clone(),
 
             module,
+            kernel: None,
+            bind_group_layout: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Creates a pipeline builder for one of tufa's built-in kernels. Each
+    /// `(key, value)` pair in `defines` is substituted into the source before
+    /// it is compiled, and the resulting module and pipeline are cached.
+    pub(crate) fn kernel(&self, source: &str, defines: &[(&str, &str)]) -> ComputePipelineBuilder {
+        let source = shader_cache::expand(source, defines);
+        ComputePipelineBuilder {
+            gpu: self.clone(),
+
+            module: self.shader_cache.module(&self.device, &source),
+            kernel: Some(source),
             bind_group_layout: Vec::new(),
             entries: Vec::new(),
         }
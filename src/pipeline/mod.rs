@@ -1,5 +1,7 @@
 //! Compute and render pipelines.
 
+use wgpu::{BindingType, BufferBindingType, StorageTextureAccess};
+
 use crate::bindings::BindableResourceId;
 
 pub mod compute;
@@ -9,3 +11,15 @@ pub(crate) struct PipelineStatus {
     pub resources: Vec<BindableResourceId>,
     pub dirty: bool,
 }
+
+/// Whether a shader is able to write to a resource bound with this type.
+pub(crate) fn is_writable(ty: &BindingType) -> bool {
+    match ty {
+        BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            ..
+        } => !read_only,
+        BindingType::StorageTexture { access, .. } => *access != StorageTextureAccess::ReadOnly,
+        _ => false,
+    }
+}
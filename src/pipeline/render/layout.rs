@@ -0,0 +1,161 @@
+//! Describing how vertex buffers are laid out. With the `derive` feature
+//! enabled, [`VertexLayout`] can be derived for structs that also derive
+//! `ShaderType`:
+//!
+//! ```rust,ignore
+//! #[derive(ShaderType, tufa::Vertex)]
+//! #[vertex(instance)]
+//! struct Particle {
+//!     position: Vector3<f32>,
+//!     #[location(2)]
+//!     color: Vector4<f32>,
+//! }
+//!
+//! let pipeline = gpu
+//!     .render_pipeline(include_wgsl!("particle.wgsl"))
+//!     .instance_layout(Particle::LAYOUT)
+//!     .finish();
+//! ```
+
+use encase::{ShaderSize, ShaderType};
+use nalgebra::{Vector2, Vector3, Vector4};
+use wgpu::{VertexBufferLayout, VertexFormat};
+
+use crate::bindings::buffer;
+
+/// A type with a known vertex buffer layout.
+pub trait VertexLayout {
+    const LAYOUT: VertexBufferLayout<'static>;
+}
+
+/// A type that can be used as a vertex attribute.
+pub trait VertexAttributeType: ShaderSize {
+    const FORMAT: VertexFormat;
+}
+
+macro_rules! attribute_types {
+    ($($ty:ty => $format:ident),* $(,)?) => {
+        $(
+            impl VertexAttributeType for $ty {
+                const FORMAT: VertexFormat = VertexFormat::$format;
+            }
+        )*
+    };
+}
+
+attribute_types! {
+    f32 => Float32,
+    u32 => Uint32,
+    i32 => Sint32,
+    Vector2<f32> => Float32x2,
+    Vector3<f32> => Float32x3,
+    Vector4<f32> => Float32x4,
+    Vector2<u32> => Uint32x2,
+    Vector3<u32> => Uint32x3,
+    Vector4<u32> => Uint32x4,
+    Vector2<i32> => Sint32x2,
+    Vector3<i32> => Sint32x3,
+    Vector4<i32> => Sint32x4,
+}
+
+/// The offset of a struct field of type `T` following a field that ends at
+/// `end`, as laid out by encase.
+#[doc(hidden)]
+pub const fn attribute_offset<T: ShaderType>(end: u64) -> u64 {
+    T::METADATA.alignment().round_up(end)
+}
+
+#[doc(hidden)]
+pub const fn attribute_end<T: ShaderSize>(offset: u64) -> u64 {
+    offset + T::SHADER_SIZE.get()
+}
+
+#[doc(hidden)]
+pub const fn array_stride<T: ShaderSize>() -> u64 {
+    buffer::array_stride::<T>()
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use encase::ShaderType;
+    use nalgebra::{Vector2, Vector3, Vector4};
+    use wgpu::{VertexAttribute, VertexFormat, VertexStepMode};
+
+    use super::VertexLayout;
+
+    #[derive(ShaderType, crate::Vertex)]
+    struct Packed {
+        position: Vector3<f32>,
+        id: u32,
+        uv: Vector2<f32>,
+    }
+
+    #[derive(ShaderType, crate::Vertex)]
+    #[vertex(instance)]
+    struct Padded {
+        scale: f32,
+        #[location(3)]
+        color: Vector4<f32>,
+        offset: Vector3<i32>,
+    }
+
+    fn attribute(format: VertexFormat, offset: u64, shader_location: u32) -> VertexAttribute {
+        VertexAttribute {
+            format,
+            offset,
+            shader_location,
+        }
+    }
+
+    #[test]
+    fn packed() {
+        let layout = Packed::LAYOUT;
+        assert_eq!(layout.array_stride, 32);
+        assert_eq!(layout.step_mode, VertexStepMode::Vertex);
+        assert_eq!(
+            layout.attributes,
+            [
+                attribute(VertexFormat::Float32x3, 0, 0),
+                attribute(VertexFormat::Uint32, 12, 1),
+                attribute(VertexFormat::Float32x2, 16, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn padded_instance() {
+        let layout = Padded::LAYOUT;
+        assert_eq!(layout.array_stride, 48);
+        assert_eq!(layout.step_mode, VertexStepMode::Instance);
+        assert_eq!(
+            layout.attributes,
+            [
+                attribute(VertexFormat::Float32, 0, 0),
+                attribute(VertexFormat::Float32x4, 16, 3),
+                attribute(VertexFormat::Sint32x3, 32, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_encase() {
+        let mut bytes = Vec::new();
+        encase::StorageBuffer::new(&mut bytes)
+            .write(&vec![Padded {
+                scale: 1.0,
+                color: Vector4::repeat(2.0),
+                offset: Vector3::repeat(-3),
+            }])
+            .unwrap();
+
+        let layout = Padded::LAYOUT;
+        assert_eq!(bytes.len() as u64, layout.array_stride);
+        let read = |index: usize| {
+            let offset = layout.attributes[index].offset as usize;
+            <[u8; 4]>::try_from(&bytes[offset..offset + 4]).unwrap()
+        };
+        assert_eq!(f32::from_le_bytes(read(0)), 1.0);
+        assert_eq!(f32::from_le_bytes(read(1)), 2.0);
+        assert_eq!(i32::from_le_bytes(read(2)), -3);
+    }
+}
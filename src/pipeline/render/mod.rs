@@ -1,20 +1,22 @@
-use std::ops::Range;
+use std::{collections::HashMap, ops::Range};
 
 use consts::VERTEX_BUFFER_LAYOUT;
 use encase::ShaderType;
+use layout::VertexLayout;
 use nalgebra::{Vector2, Vector4};
+use parking_lot::MappedRwLockReadGuard;
 use wgpu::{
-    BindGroup, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BlendComponent, BlendState,
-    ColorTargetState, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState,
-    FragmentState, IndexFormat, MultisampleState, PipelineCompilationOptions,
+    BindGroup, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BlendComponent, BlendState, Buffer,
+    BufferUsages, ColorTargetState, ColorWrites, CompareFunction, DepthBiasState,
+    DepthStencilState, FragmentState, IndexFormat, MultisampleState, PipelineCompilationOptions,
     PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, RenderPass, ShaderModule,
     ShaderModuleDescriptor, ShaderStages, StencilState, VertexBufferLayout, VertexState,
 };
 
 use crate::{
     bindings::{
-        buffer::{IndexBuffer, VertexBuffer},
-        Bindable, BindableResourceId,
+        buffer::{IndexBuffer, IndexType, VertexBinding},
+        Bindable, BindableResourceId, Bindings,
     },
     gpu::Gpu,
     misc::ids::PipelineId,
@@ -23,6 +25,7 @@ use crate::{
 
 use super::PipelineStatus;
 pub mod consts;
+pub mod layout;
 pub mod pass;
 
 #[derive(Clone, Debug, ShaderType)]
@@ -31,6 +34,10 @@ pub struct Vertex {
     pub uv: Vector2<f32>,
 }
 
+impl VertexLayout for Vertex {
+    const LAYOUT: VertexBufferLayout<'static> = VERTEX_BUFFER_LAYOUT;
+}
+
 impl Vertex {
     pub const fn new(position: Vector4<f32>, uv: Vector2<f32>) -> Self {
         Self { position, uv }
@@ -44,6 +51,8 @@ pub struct RenderPipeline {
     pipeline: wgpu::RenderPipeline,
     entries: Vec<BindableResourceId>,
     bind_group: BindGroup,
+    bind_groups: HashMap<Vec<bool>, BindGroup>,
+    strip_index_format: Option<IndexFormat>,
 }
 
 #[derive(Clone)]
@@ -57,33 +66,58 @@ pub struct RenderPipelineBuilder {
     bind_group: Vec<BindableResourceId>,
 
     topology: PrimitiveTopology,
+    strip_index_format: Option<IndexFormat>,
     depth_compare: CompareFunction,
 }
 
 impl RenderPipeline {
     fn recreate_bind_group(&mut self) {
-        if self.gpu.binding_manager.get_pipeline(self.id).dirty {
-            self.bind_group = self.gpu.binding_manager.create_bind_group(
-                &self.gpu.device,
-                &self.pipeline.get_bind_group_layout(0),
-                &self.entries,
+        self.bind_group = self.gpu.binding_manager.bind_group(
+            &self.gpu.device,
+            &self.pipeline.get_bind_group_layout(0),
+            self.id,
+            &self.entries,
+            &mut self.bind_groups,
+        );
+    }
+
+    fn buffer(&self, buffer: &impl VertexBinding) -> MappedRwLockReadGuard<Buffer> {
+        let buffer = MappedRwLockReadGuard::map(
+            self.gpu.binding_manager.get_resource(buffer.get_id()),
+            |x| x.expect_buffer(),
+        );
+
+        assert!(
+            buffer.usage().contains(BufferUsages::VERTEX),
+            "Buffer must be built with `vertex` to be drawn"
+        );
+        buffer
+    }
+
+    fn set_index_buffer(&self, render_pass: &mut RenderPass, buffer: &Buffer, format: IndexFormat) {
+        if let Some(strip_index_format) = self.strip_index_format {
+            assert_eq!(
+                format, strip_index_format,
+                "Index buffer format must match the pipeline's strip index format"
             );
         }
+
+        render_pass.set_index_buffer(buffer.slice(..), format);
     }
 
-    pub fn draw<T>(
+    pub fn draw<I: IndexType>(
         &mut self,
         render_pass: &mut RenderPass,
-        index: &IndexBuffer,
-        vertex: &VertexBuffer<T>,
+        index: &IndexBuffer<I>,
+        vertex: &impl VertexBinding,
         indices: Range<u32>,
     ) {
         self.recreate_bind_group();
 
         render_pass.set_pipeline(&self.pipeline);
         render_pass.set_bind_group(0, Some(&self.bind_group), &[]);
-        render_pass.set_index_buffer(index.get().slice(..), IndexFormat::Uint32);
-        render_pass.set_vertex_buffer(0, vertex.get().slice(..));
+        self.set_index_buffer(render_pass, &index.get(), I::as_format());
+        render_pass.set_vertex_buffer(0, self.buffer(vertex).slice(..));
         render_pass.draw_indexed(indices, 0, 0..1);
     }
 
@@ -93,15 +127,15 @@ impl RenderPipeline {
 
         render_pass.set_pipeline(&self.pipeline);
         render_pass.set_bind_group(0, Some(&self.bind_group), &[]);
-        render_pass.set_index_buffer(index.get().slice(..), IndexFormat::Uint32);
+        self.set_index_buffer(render_pass, &index.get(), u32::as_format());
         render_pass.set_vertex_buffer(0, vertex.get().slice(..));
         render_pass.draw_indexed(0..6, 0, instances);
     }
 
-    pub fn instance_quad<T>(
+    pub fn instance_quad(
         &mut self,
         render_pass: &mut RenderPass,
-        instances: &VertexBuffer<T>,
+        instances: &impl VertexBinding,
         range: Range<u32>,
     ) {
         self.recreate_bind_group();
@@ -109,9 +143,9 @@ impl RenderPipeline {
 
         render_pass.set_pipeline(&self.pipeline);
         render_pass.set_bind_group(0, Some(&self.bind_group), &[]);
-        render_pass.set_index_buffer(index.get().slice(..), IndexFormat::Uint32);
+        self.set_index_buffer(render_pass, &index.get(), u32::as_format());
         render_pass.set_vertex_buffer(0, vertex.get().slice(..));
-        render_pass.set_vertex_buffer(1, instances.get().slice(..));
+        render_pass.set_vertex_buffer(1, self.buffer(instances).slice(..));
         render_pass.draw_indexed(0..6, 0, range);
     }
 }
@@ -131,6 +165,15 @@ impl RenderPipelineBuilder {
         self
     }
 
+    /// Binds every resource in the group, in order, after any already bound.
+    pub fn bind_all(mut self, bindings: &impl Bindings, visibility: ShaderStages) -> Self {
+        for entry in bindings.bindings() {
+            self = self.bind(&entry, visibility);
+        }
+
+        self
+    }
+
     pub fn vertex_layout(mut self, layout: VertexBufferLayout<'static>) -> Self {
         self.vertex_layout = layout;
         self
@@ -151,7 +194,22 @@ impl RenderPipelineBuilder {
         self
     }
 
+    /// Enables primitive restart for strip topologies. Indices of the maximum
+    /// value of `I` will start a new strip, and every index buffer drawn with
+    /// the pipeline must use `I`. The topology must be a strip by the time the
+    /// pipeline is finished.
+    pub fn strip_index_format<I: IndexType>(mut self) -> Self {
+        self.strip_index_format = Some(I::as_format());
+        self
+    }
+
     pub fn finish(self) -> RenderPipeline {
+        assert!(
+            self.strip_index_format.is_none() || self.topology.is_strip(),
+            "Strip index format requires a strip topology, not {:?}",
+            self.topology
+        );
+
         let device = &self.gpu.device;
 
         let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -194,6 +252,7 @@ impl RenderPipelineBuilder {
             }),
             primitive: PrimitiveState {
                 topology: self.topology,
+                strip_index_format: self.strip_index_format,
                 ..PrimitiveState::default()
             },
             depth_stencil: Some(DepthStencilState {
@@ -208,12 +267,6 @@ impl RenderPipelineBuilder {
             cache: None,
         });
 
-        let bind_group = self.gpu.binding_manager.create_bind_group(
-            &self.gpu.device,
-            &pipeline.get_bind_group_layout(0),
-            &self.bind_group,
-        );
-
         let id = PipelineId::new();
         self.gpu.binding_manager.add_pipeline(
             id,
@@ -223,12 +276,23 @@ impl RenderPipelineBuilder {
             },
         );
 
+        let mut bind_groups = HashMap::new();
+        let bind_group = self.gpu.binding_manager.bind_group(
+            &self.gpu.device,
+            &pipeline.get_bind_group_layout(0),
+            id,
+            &self.bind_group,
+            &mut bind_groups,
+        );
+
         RenderPipeline {
             gpu: self.gpu,
             id,
             pipeline,
             bind_group,
+            bind_groups,
             entries: self.bind_group,
+            strip_index_format: self.strip_index_format,
         }
     }
 }
@@ -246,6 +310,7 @@ impl Gpu {
             bind_group: Vec::new(),
 
             topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
             depth_compare: CompareFunction::LessEqual,
         }
     }
@@ -1,55 +1,71 @@
 use wgpu::{
-    Color, LoadOp, Operations, RenderPass, RenderPassColorAttachment,
-    RenderPassDepthStencilAttachment, RenderPassDescriptor, StoreOp,
+    Color, CommandEncoder, LoadOp, Operations, RenderPass, RenderPassColorAttachment,
+    RenderPassDepthStencilAttachment, RenderPassDescriptor, StoreOp, TextureView,
 };
 
 use crate::{
-    bindings::texture::{
-        format::{Depth, Rgba8},
-        Texture,
-    },
+    bindings::texture::{format::TextureFormat, Texture},
     gpu::Gpu,
 };
 
 impl Gpu {
-    pub fn render_pass(
+    /// Clears the textures and runs a render pass drawing into them. The depth
+    /// texture must have a depth format, like
+    /// [`Depth`](crate::bindings::texture::format::Depth).
+    pub fn render_pass<Color: TextureFormat, DepthFormat: TextureFormat>(
         &self,
-        texture: &Texture<Rgba8>,
-        depth: &Texture<Depth>,
+        texture: &Texture<Color>,
+        depth: &Texture<DepthFormat>,
         callback: impl FnOnce(&mut RenderPass),
     ) {
+        let (view, depth) = attachments(texture, depth);
         self.immediate_dispatch(|encoder| {
-            let manager = &self.binding_manager;
-
-            let view = manager.get_resource(texture.id);
-            let view = view.expect_texture_view();
-
-            let depth = manager.get_resource(depth.id);
-            let depth = depth.expect_texture_view();
-
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color::BLACK),
-                        store: StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-                    view: depth,
-                    depth_ops: Some(Operations {
-                        load: LoadOp::Clear(1.0),
-                        store: StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
+            let mut render_pass = begin_render_pass(encoder, &view, &depth);
             callback(&mut render_pass);
         });
     }
 }
+
+/// Gets the views to render into, checking that the depth texture has a depth
+/// format.
+pub(crate) fn attachments<Color: TextureFormat, DepthFormat: TextureFormat>(
+    texture: &Texture<Color>,
+    depth: &Texture<DepthFormat>,
+) -> (TextureView, TextureView) {
+    let format = DepthFormat::as_format();
+    assert!(
+        format.has_depth_aspect(),
+        "{format:?} textures can't be used as depth attachments"
+    );
+
+    (texture.attachment(), depth.attachment())
+}
+
+/// Starts a render pass that clears and draws into the color and depth views.
+pub(crate) fn begin_render_pass<'a>(
+    encoder: &'a mut CommandEncoder,
+    view: &TextureView,
+    depth: &TextureView,
+) -> RenderPass<'a> {
+    encoder.begin_render_pass(&RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(Color::BLACK),
+                store: StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+            view: depth,
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    })
+}
@@ -0,0 +1,290 @@
+//! Saving GPU resources to disk and loading them back, so long running
+//! simulations can be resumed after the process exits.
+//!
+//! ```rust,ignore
+//! let resources: [&dyn Snapshottable; 2] = [&particles, &field];
+//! if Path::new("checkpoint.tufa").exists() {
+//!     gpu.restore("checkpoint.tufa", &resources)?;
+//! }
+//!
+//! for step in 0.. {
+//!     simulate.dispatch(workgroups);
+//!     if step % 1000 == 0 {
+//!         gpu.snapshot("checkpoint.tufa", &resources)?;
+//!     }
+//! }
+//! ```
+//!
+//! Snapshots only store the raw bytes of each resource along with its
+//! [`SnapshotLayout`], so they must be restored into handles of the same types,
+//! passed in the same order.
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use encase::{
+    internal::{CreateFrom, WriteInto},
+    ShaderType,
+};
+
+use crate::{
+    bindings::{
+        buffer::{download, mutability::Mutability, StorageBuffer, UniformBuffer},
+        texture::{format::TextureFormat, Texture},
+    },
+    gpu::Gpu,
+};
+
+const MAGIC: &[u8; 8] = b"TUFASNAP";
+const VERSION: u32 = 1;
+
+/// A resource whose contents can be saved with [`Gpu::snapshot`].
+pub trait Snapshottable {
+    /// Describes the resource, so snapshots can't be restored into the wrong
+    /// kind of handle. Errors if the resource can't be snapshotted.
+    fn layout(&self) -> Result<SnapshotLayout>;
+
+    /// Copies the resource's contents back to the CPU.
+    fn save(&self) -> Vec<u8>;
+
+    /// Replaces the resource's contents. Only called with data that was saved
+    /// from a resource with the same layout.
+    fn load(&self, data: &[u8]);
+}
+
+/// What a snapshotted resource is, and the size of the type it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotLayout {
+    pub kind: SnapshotKind,
+    /// The minimum size of the buffer's type, or the size of a texel.
+    pub type_size: u64,
+    /// The size of a texture, or zero for buffers.
+    pub extent: [u32; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotKind {
+    StorageBuffer,
+    UniformBuffer,
+    Texture,
+}
+
+impl Gpu {
+    /// Saves the contents of each resource to `path`. The file is written to
+    /// `path` with `.partial` appended first and then moved into place, so an
+    /// existing snapshot isn't lost if the process dies partway through.
+    pub fn snapshot(&self, path: impl AsRef<Path>, resources: &[&dyn Snapshottable]) -> Result<()> {
+        let path = path.as_ref();
+        let layouts = resources
+            .iter()
+            .map(|x| x.layout())
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".partial");
+        let temp = PathBuf::from(temp);
+
+        let mut file = BufWriter::new(File::create(&temp)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&(resources.len() as u32).to_le_bytes())?;
+
+        for (resource, layout) in resources.iter().zip(layouts) {
+            let data = resource.save();
+            layout.write(&mut file)?;
+            file.write_all(&(data.len() as u64).to_le_bytes())?;
+            file.write_all(&data)?;
+        }
+
+        file.into_inner()?.sync_all()?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Loads a snapshot from `path` into each resource, which must match the
+    /// resources it was saved from. Nothing is modified if any of them don't.
+    pub fn restore(&self, path: impl AsRef<Path>, resources: &[&dyn Snapshottable]) -> Result<()> {
+        let path = path.as_ref();
+        let mut file = BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        );
+        let file_len = file.get_ref().metadata()?.len();
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        ensure!(&magic == MAGIC, "{} is not a tufa snapshot", path.display());
+
+        let version = read_u32(&mut file)?;
+        ensure!(
+            version == VERSION,
+            "Unsupported snapshot version {version}, expected {VERSION}"
+        );
+
+        let count = read_u32(&mut file)? as usize;
+        ensure!(
+            count == resources.len(),
+            "Snapshot has {count} resources, but {} were given",
+            resources.len()
+        );
+
+        let mut entries = Vec::with_capacity(count);
+        for (i, resource) in resources.iter().enumerate() {
+            let saved = SnapshotLayout::read(&mut file)?;
+            let expected = resource.layout()?;
+            if saved != expected {
+                bail!("Resource {i} was saved as {saved:?}, but is {expected:?}");
+            }
+
+            let len = read_u64(&mut file)?;
+            if let Some(size) = expected.byte_size() {
+                ensure!(
+                    len == size,
+                    "Resource {i} was saved with {len} bytes, but should have {size}"
+                );
+            }
+
+            let remaining = file_len.saturating_sub(file.stream_position()?);
+            ensure!(
+                len <= remaining,
+                "Resource {i} was saved with {len} bytes, but only {remaining} are left in the file"
+            );
+
+            let mut data = vec![0; len as usize];
+            file.read_exact(&mut data)?;
+            entries.push(data);
+        }
+
+        for (resource, data) in resources.iter().zip(entries) {
+            resource.load(&data);
+        }
+
+        Ok(())
+    }
+}
+
+impl SnapshotLayout {
+    /// The number of bytes the resource's contents take up, if it can be known
+    /// from the layout. Buffers can hold a different number of elements than
+    /// when they were saved, since loading reallocates them.
+    fn byte_size(&self) -> Option<u64> {
+        match self.kind {
+            SnapshotKind::Texture => Some(
+                self.extent
+                    .iter()
+                    .fold(self.type_size, |size, &x| size * x as u64),
+            ),
+            SnapshotKind::StorageBuffer | SnapshotKind::UniformBuffer => None,
+        }
+    }
+
+    fn write(&self, out: &mut impl Write) -> Result<()> {
+        out.write_all(&(self.kind as u32).to_le_bytes())?;
+        out.write_all(&self.type_size.to_le_bytes())?;
+        for size in self.extent {
+            out.write_all(&size.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn read(input: &mut impl Read) -> Result<Self> {
+        let kind = match read_u32(input)? {
+            0 => SnapshotKind::StorageBuffer,
+            1 => SnapshotKind::UniformBuffer,
+            2 => SnapshotKind::Texture,
+            kind => bail!("Unknown resource kind {kind}"),
+        };
+
+        Ok(Self {
+            kind,
+            type_size: read_u64(input)?,
+            extent: [read_u32(input)?, read_u32(input)?, read_u32(input)?],
+        })
+    }
+}
+
+fn read_u32(input: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+impl<T, Mut> Snapshottable for StorageBuffer<T, Mut>
+where
+    T: ShaderType + WriteInto + CreateFrom,
+    Mut: Mutability,
+{
+    fn layout(&self) -> Result<SnapshotLayout> {
+        Ok(SnapshotLayout {
+            kind: SnapshotKind::StorageBuffer,
+            type_size: T::min_size().get(),
+            extent: [0; 3],
+        })
+    }
+
+    fn save(&self) -> Vec<u8> {
+        match self.byte_len() {
+            0 => Vec::new(),
+            len => download::download(&self.gpu, &self.get(), 0..len),
+        }
+    }
+
+    fn load(&self, data: &[u8]) {
+        self.upload_bytes(data, true);
+    }
+}
+
+impl<T: ShaderType + WriteInto + CreateFrom> Snapshottable for UniformBuffer<T> {
+    fn layout(&self) -> Result<SnapshotLayout> {
+        Ok(SnapshotLayout {
+            kind: SnapshotKind::UniformBuffer,
+            type_size: T::min_size().get(),
+            extent: [0; 3],
+        })
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let buffer = self.get();
+        download::download(&self.gpu, &buffer, 0..buffer.size())
+    }
+
+    fn load(&self, data: &[u8]) {
+        self.upload_bytes(data);
+    }
+}
+
+impl<Format: TextureFormat> Snapshottable for Texture<Format> {
+    /// Errors for depth textures, since their texels can't be uploaded from a
+    /// buffer when restoring.
+    fn layout(&self) -> Result<SnapshotLayout> {
+        let format = Format::as_format();
+        let Some(type_size) = Format::bytes_per_texel().filter(|_| !format.has_depth_aspect())
+        else {
+            bail!("{format:?} textures can't be restored from a snapshot");
+        };
+
+        Ok(SnapshotLayout {
+            kind: SnapshotKind::Texture,
+            type_size: type_size as u64,
+            extent: self.size.into(),
+        })
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.download()
+    }
+
+    fn load(&self, data: &[u8]) {
+        self.upload(data);
+    }
+}
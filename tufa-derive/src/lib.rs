@@ -0,0 +1,251 @@
+//! Derive macros for [tufa](https://docs.rs/tufa). Enable tufa's `derive`
+//! feature rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::ParseStream, parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields,
+    FieldsNamed, Ident, LitInt, LitStr, Result, Token,
+};
+
+/// Implements `tufa::pipeline::render::VertexLayout` for a struct that also
+/// derives `ShaderType`. Attribute offsets follow encase's layout, so the
+/// struct can be uploaded with `Gpu::create_vertex`.
+///
+/// Fields are assigned consecutive shader locations starting at zero, which
+/// can be changed with `#[location(n)]`; following fields continue counting
+/// from there. Two fields can't share a location. Adding `#[vertex(instance)]` to the struct makes the layout
+/// step per instance rather than per vertex.
+#[proc_macro_derive(Vertex, attributes(location, vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    vertex(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `tufa::bindings::Bindings` for a struct of resources, such as
+/// buffers, textures, and samplers, or references to them.
+///
+/// Fields are bound in order starting at zero. `#[binding(n)]` moves a field
+/// to binding `n`, with following fields counting up from there. Bindings
+/// must end up contiguous, as pipeline builders number them sequentially.
+/// The WGSL type used by `Bindings::wgsl` can be given with
+/// `#[binding(ty = "array<Particle>")]`; otherwise the field must implement
+/// `WgslBinding`.
+#[proc_macro_derive(Bindings, attributes(binding))]
+pub fn derive_bindings(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    bindings(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn bindings(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = named_fields(&input, "Bindings")?;
+
+    let mut entries = Vec::new();
+    let mut next = 0;
+    for field in fields.named.iter() {
+        let (mut binding, mut ty) = (None, None);
+        for attr in field.attrs.iter().filter(|x| x.path().is_ident("binding")) {
+            attr.parse_args_with(|input: ParseStream| {
+                while !input.is_empty() {
+                    if input.peek(LitInt) {
+                        binding = Some(input.parse::<LitInt>()?.base10_parse::<u32>()?);
+                    } else {
+                        let key = input.parse::<Ident>()?;
+                        if key != "ty" {
+                            return Err(Error::new(key.span(), "expected a binding or `ty`"));
+                        }
+
+                        input.parse::<Token![=]>()?;
+                        ty = Some(input.parse::<LitStr>()?);
+                    }
+
+                    if !input.is_empty() {
+                        input.parse::<Token![,]>()?;
+                    }
+                }
+
+                Ok(())
+            })?;
+        }
+
+        let binding = binding.unwrap_or(next);
+        entries.push((binding, field.ident.clone().unwrap(), ty));
+        next = binding + 1;
+    }
+
+    entries.sort_by_key(|(binding, ..)| *binding);
+    for (i, (binding, field, _)) in entries.iter().enumerate() {
+        if *binding != i as u32 {
+            return Err(Error::new(
+                field.span(),
+                format!(
+                    "bindings must be contiguous from zero, but `{field}` is binding {binding}"
+                ),
+            ));
+        }
+    }
+
+    let wgsl = quote!(::tufa::bindings::wgsl);
+    let fields = entries.iter().map(|(_, field, _)| field);
+    let declarations = entries.iter().map(|(binding, field, ty)| {
+        let ty = match ty {
+            Some(ty) => quote!(#ty),
+            None => quote!(&#wgsl::WgslBinding::wgsl_type(&self.#field)),
+        };
+        let field_name = field.to_string();
+
+        quote!(out.push_str(&#wgsl::declaration(0, #binding, #field_name, &self.#field, #ty));)
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::tufa::bindings::Bindings for #name #ty_generics #where_clause {
+            fn bindings(&self) -> ::std::vec::Vec<&dyn ::tufa::bindings::Bindable> {
+                ::std::vec![#(&self.#fields),*]
+            }
+
+            fn wgsl(&self) -> ::std::string::String {
+                let mut out = ::std::string::String::new();
+                #(#declarations)*
+                out
+            }
+        }
+    })
+}
+
+fn named_fields<'a>(input: &'a DeriveInput, derive: &str) -> Result<&'a FieldsNamed> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            format!("{derive} can only be derived for structs"),
+        ));
+    };
+
+    match &data.fields {
+        Fields::Named(fields) => Ok(fields),
+        _ => Err(Error::new(
+            data.fields.span(),
+            format!("{derive} can only be derived for structs with named fields"),
+        )),
+    }
+}
+
+fn vertex(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "Vertex can't be derived for generic structs",
+        ));
+    }
+
+    let fields = named_fields(&input, "Vertex")?;
+
+    let mut step_mode = quote!(Vertex);
+    for attr in input.attrs.iter().filter(|x| x.path().is_ident("vertex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("instance") {
+                step_mode = quote!(Instance);
+                Ok(())
+            } else {
+                Err(meta.error("expected `instance`"))
+            }
+        })?;
+    }
+
+    let layout = quote!(::tufa::pipeline::render::layout);
+    let mut attributes = Vec::new();
+    let mut end = quote!(0);
+    let mut location: u32 = 0;
+    let mut used = Vec::new();
+
+    for field in fields.named.iter() {
+        for attr in field.attrs.iter() {
+            if attr.path().is_ident("location") {
+                location = attr.parse_args::<LitInt>()?.base10_parse()?;
+            } else if attr.path().is_ident("align") || attr.path().is_ident("size") {
+                return Err(Error::new(
+                    attr.span(),
+                    "Vertex doesn't support encase's `align` and `size` attributes",
+                ));
+            }
+        }
+
+        if used.contains(&location) {
+            return Err(Error::new(
+                field.span(),
+                format!("Location {location} is already used by another field"),
+            ));
+        }
+        used.push(location);
+
+        let ty = &field.ty;
+        let offset = quote!(#layout::attribute_offset::<#ty>(#end));
+        attributes.push(quote! {
+            ::tufa::export::wgpu::VertexAttribute {
+                format: <#ty as #layout::VertexAttributeType>::FORMAT,
+                offset: #offset,
+                shader_location: #location,
+            }
+        });
+
+        end = quote!(#layout::attribute_end::<#ty>(#offset));
+        location += 1;
+    }
+
+    Ok(quote! {
+        impl #layout::VertexLayout for #name {
+            const LAYOUT: ::tufa::export::wgpu::VertexBufferLayout<'static> =
+                ::tufa::export::wgpu::VertexBufferLayout {
+                    array_stride: #layout::array_stride::<Self>(),
+                    step_mode: ::tufa::export::wgpu::VertexStepMode::#step_mode,
+                    attributes: &[#(#attributes),*],
+                };
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    #[test]
+    fn duplicate_location() {
+        let error = super::vertex(parse_quote! {
+            struct Vertex {
+                #[location(1)]
+                a: f32,
+                b: f32,
+                #[location(2)]
+                c: f32,
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Location 2 is already used by another field"
+        );
+        assert!(error
+            .into_compile_error()
+            .to_string()
+            .contains("compile_error"));
+    }
+
+    #[test]
+    fn rejects_encase_attributes() {
+        assert!(super::vertex(parse_quote! {
+            struct Vertex {
+                #[align(16)]
+                a: f32,
+            }
+        })
+        .is_err());
+    }
+}